@@ -0,0 +1,131 @@
+use reqwest::Url;
+
+use rust_analyzer_downloader::services::transport::{Error, Request, Response, Transport};
+
+/// Host of release downloads from GitHub, it redirects to a CDN and reqwest
+/// drops the token when following a redirect to another host.
+const GITHUB_HOST: &str = "github.com";
+
+/// Adds the GitHub token to requests for the releases API and github.com only,
+/// so a download mirror never receives it.
+#[derive(Clone)]
+pub(crate) struct AuthTransport<T> {
+    inner: T,
+    hosts: Vec<String>,
+    authorization: Option<String>,
+}
+
+impl<T> std::fmt::Debug for AuthTransport<T>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthTransport")
+            .field("inner", &self.inner)
+            .field("hosts", &self.hosts)
+            .field(
+                "authorization",
+                &self.authorization.as_ref().map(|_| "<redacted>"),
+            )
+            .finish()
+    }
+}
+
+fn host(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+}
+
+impl<T> AuthTransport<T> {
+    /// Sends the token to the host of `api_url` and to github.com.
+    pub(crate) fn new(inner: T, api_url: &str, token: Option<String>) -> Self {
+        let mut hosts = vec![GITHUB_HOST.to_string()];
+        hosts.extend(host(api_url));
+
+        Self {
+            inner,
+            hosts,
+            authorization: token.map(|token| format!("Bearer {}", token)),
+        }
+    }
+
+    fn authorize(&self, request: Request) -> Request {
+        let authorization = match &self.authorization {
+            Some(authorization) => authorization,
+            None => return request,
+        };
+
+        match host(&request.url) {
+            Some(host) if self.hosts.contains(&host) => {
+                request.header("Authorization", authorization.as_str())
+            }
+            _ => request,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T> Transport for AuthTransport<T>
+where
+    T: Transport,
+{
+    async fn get(&self, request: Request) -> Result<Response, Error> {
+        self.inner.get(self.authorize(request)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_analyzer_downloader::services::transport::MemoryTransport;
+
+    fn authorization(request: &Request) -> Option<&str> {
+        request
+            .headers
+            .iter()
+            .find(|(name, _)| name == "Authorization")
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[tokio::test]
+    async fn test_token_is_only_sent_to_github() {
+        let transport = AuthTransport::new(
+            MemoryTransport::new(),
+            "https://api.github.com/repos/rust-lang/rust-analyzer/releases",
+            Some("secret".into()),
+        );
+
+        for url in [
+            "https://api.github.com/repos/rust-lang/rust-analyzer/releases",
+            "https://github.com/rust-lang/rust-analyzer/releases/download/2022-08-22/rust-analyzer.gz",
+            "https://mirror.example.com/download/2022-08-22/rust-analyzer.gz",
+        ] {
+            transport.get(Request::new(url)).await.unwrap();
+        }
+
+        let requests = transport.inner.requests();
+        assert_eq!(authorization(&requests[0]), Some("Bearer secret"));
+        assert_eq!(authorization(&requests[1]), Some("Bearer secret"));
+        assert_eq!(authorization(&requests[2]), None);
+    }
+
+    #[tokio::test]
+    async fn test_token_follows_api_mirror() {
+        let transport = AuthTransport::new(
+            MemoryTransport::new(),
+            "https://ghe.example.com/api/v3/repos/rust-lang/rust-analyzer/releases",
+            Some("secret".into()),
+        );
+
+        transport
+            .get(Request::new("https://ghe.example.com/api/v3/x"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            authorization(&transport.inner.requests()[0]),
+            Some("Bearer secret")
+        );
+    }
+}
@@ -1,17 +1,54 @@
 use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use futures::future::join_all;
+use serde::Serialize;
 use time::ext::NumericalDuration;
 use time::format_description::FormatItem;
 use time::parsing::Parsable;
 use time::{format_description, Date};
 use tracing::{debug, info, warn};
 
-use super::command::{Command, Errors};
-use rust_analyzer_downloader::rust_analyzer::version::{get, Error as VersionError, Version};
-use rust_analyzer_downloader::services::downloader::Downloader;
+use super::command::{Command, Errors, Report, EXIT_SUCCESS, EXIT_UPDATE_AVAILABLE};
+use super::download::print_install;
+use crate::state::{modified, Installed, LastCheck};
+use rust_analyzer_downloader::fs::same_file;
+use rust_analyzer_downloader::rust_analyzer::version::{
+    inspect, which, Error as VersionError, Version, DEFAULT_TIMEOUT,
+};
+use rust_analyzer_downloader::services::downloader::{Downloader, InstallReport};
 use rust_analyzer_downloader::services::versions::{Paging, ReleasesJsonResponse, Versions};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum Action {
+    None,
+    Downloaded,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct CheckReport {
+    output: String,
+    current: Option<Version>,
+    latest: Option<String>,
+    update_available: bool,
+    action: Action,
+    /// Releases came from the state file instead of the releases API
+    cached: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    install: Option<InstallReport>,
+}
+
+/// Where the last release lookup is recorded and when it may be reused.
+#[derive(Debug)]
+pub(super) struct Cache {
+    pub(super) path: PathBuf,
+    pub(super) api_url: String,
+    pub(super) min_interval: Option<Duration>,
+    pub(super) force: bool,
+}
+
 #[derive(Debug)]
 pub(super) struct CheckCommand {
     output: String,
@@ -19,6 +56,9 @@ pub(super) struct CheckCommand {
     nightly: bool,
     downloader: Downloader,
     versions: Versions,
+    cache: Option<Cache>,
+    /// Allows asking on the terminal before changing the shell configuration
+    interactive: bool,
     date_format: Vec<FormatItem<'static>>,
 }
 
@@ -37,9 +77,39 @@ impl CheckCommand {
             versions,
             should_download,
             nightly,
+            cache: None,
+            interactive: false,
             date_format: format_description::parse("[year]-[month]-[day]").unwrap(),
         }
     }
+
+    pub(super) fn with_cache(mut self, cache: Option<Cache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub(super) fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+}
+
+/// Records the checked binary so `rad prompt` can answer without running it.
+async fn record(last: &mut LastCheck, report: &CheckReport) {
+    let version = match report.action {
+        Action::Downloaded => inspect(&report.output, DEFAULT_TIMEOUT).await.ok(),
+        Action::None => report.current.clone(),
+    };
+
+    last.installed = match (version, modified(Path::new(&report.output))) {
+        (Some(version), Some(modified)) => Some(Installed {
+            path: report.output.clone(),
+            modified,
+            version,
+            update_available: report.exit_code() == EXIT_UPDATE_AVAILABLE,
+        }),
+        _ => None,
+    };
 }
 
 fn compare_versions<T>(
@@ -59,17 +129,61 @@ where
 }
 
 impl CheckCommand {
+    /// Latest releases, from the state file when the last lookup is recent enough.
+    async fn releases(&self, cache: Option<&Cache>) -> Result<(LastCheck, bool), Errors> {
+        if let Some(cache) = cache.filter(|cache| !cache.force) {
+            let last = cache.min_interval.and_then(|interval| {
+                LastCheck::load(&cache.path).filter(|last| last.is_fresh(&cache.api_url, interval))
+            });
+
+            if let Some(last) = last {
+                debug!(path = %cache.path.display(), "Using releases from the last check");
+                return Ok((last, true));
+            }
+        }
+
+        let releases = match self.versions.get(1, 2).await? {
+            Paging::Next(_, data) => data,
+            Paging::Done => Vec::new(),
+        };
+        let api_url = cache.map(|cache| cache.api_url.clone()).unwrap_or_default();
+
+        Ok((LastCheck::new(api_url, releases), false))
+    }
+
+    fn warn_if_shadowed(&self) {
+        let on_path = match which() {
+            Some(path) => path,
+            None => {
+                warn!(
+                    output = self.output.as_str(),
+                    "No rust-analyzer found on PATH, editors may not pick up the checked binary"
+                );
+                return;
+            }
+        };
+
+        if !same_file(&on_path, Path::new(&self.output)) {
+            warn!(
+                output = self.output.as_str(),
+                on_path = %on_path.display(),
+                "rust-analyzer on PATH is a different binary than the one being checked"
+            );
+        }
+    }
+
     async fn download(
         self,
-        data: Vec<ReleasesJsonResponse>,
+        data: &[ReleasesJsonResponse],
         current_version: Option<Version>,
-    ) -> Result<(), Errors> {
+        cached: bool,
+    ) -> Result<CheckReport, Errors> {
         let futures = data.iter().map(|release| async {
             let release = release.tag_name.as_str();
 
             if !self.nightly && release == "nightly" {
                 debug!("nightly rust-analyzer is not enabled, skipping...");
-                return Ok(());
+                return Ok(None);
             }
 
             if self.nightly && release != "nightly" {
@@ -77,7 +191,7 @@ impl CheckCommand {
                     "nightly rust-analyzer is enabled, skipping version {}...",
                     release
                 );
-                return Ok(());
+                return Ok(None);
             }
 
             let new_version_exists = if self.nightly && release == "nightly" {
@@ -97,16 +211,24 @@ impl CheckCommand {
                 }
             };
 
+            let mut action = Action::None;
+            let mut install = None;
+
             if new_version_exists {
                 if self.should_download {
-                    self.downloader
-                        .download(release, self.output.as_str())
-                        .await?;
+                    install = Some(
+                        self.downloader
+                            .download(release, self.output.as_str())
+                            .await?,
+                    );
 
                     info!(
                         release = release,
                         "Downloaded version successfully downloaded"
                     );
+
+                    crate::shell::ensure_in_path(&self.output, self.interactive).await;
+                    action = Action::Downloaded;
                 } else {
                     info!(release = release, "New version available");
                 }
@@ -114,27 +236,64 @@ impl CheckCommand {
                 info!("Current version is up to date");
             }
 
-            Result::<(), Errors>::Ok(())
+            Result::<_, Errors>::Ok(Some((
+                release.to_string(),
+                new_version_exists,
+                action,
+                install,
+            )))
         });
 
-        let result = join_all(futures)
-            .await
-            .drain(..)
-            .find(|result| result.is_err());
+        let mut results = join_all(futures).await;
 
-        match result {
-            Some(Err(err)) => Err(err),
-            None | Some(Ok(_)) => Ok(()),
+        if let Some(index) = results.iter().position(|result| result.is_err()) {
+            return Err(results.swap_remove(index).unwrap_err());
+        }
+
+        let checked = results.into_iter().find_map(|result| result.ok().flatten());
+        let (latest, update_available, action, install) = match checked {
+            Some((latest, update_available, action, install)) => {
+                (Some(latest), update_available, action, install)
+            }
+            None => (None, false, Action::None, None),
+        };
+
+        Ok(CheckReport {
+            output: self.output,
+            current: current_version,
+            latest,
+            update_available,
+            action,
+            cached,
+            install,
+        })
+    }
+}
+
+impl Report for CheckReport {
+    fn print(&self) {
+        if let Some(install) = &self.install {
+            print_install(install);
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        if self.update_available && self.action == Action::None {
+            EXIT_UPDATE_AVAILABLE
+        } else {
+            EXIT_SUCCESS
         }
     }
 }
 
 #[async_trait::async_trait]
 impl Command for CheckCommand {
-    async fn execute(self) -> Result<(), Errors> {
-        let current_version = match get().await {
+    type Output = CheckReport;
+
+    async fn execute(mut self) -> Result<CheckReport, Errors> {
+        let current_version = match inspect(&self.output, DEFAULT_TIMEOUT).await {
             Ok(version) => Some(version),
-            Err(VersionError::Io(err)) if err.kind() == ErrorKind::NotFound => {
+            Err(VersionError::Io { source, .. }) if source.kind() == ErrorKind::NotFound => {
                 warn!("No rust-analyzer binary found, downloading latest version");
                 None
             }
@@ -144,23 +303,44 @@ impl Command for CheckCommand {
             }
         };
 
-        if current_version.is_some() {
-            let current_version = current_version.as_ref().unwrap();
+        self.warn_if_shadowed();
+
+        if let Some(current_version) = &current_version {
             debug!(
                 "Current version is {} (Semantic Version: {})",
                 current_version.date_version, current_version.semantic_version
             );
         }
 
-        let version = self.versions.get(1, 2).await?;
+        let cache = self.cache.take();
+        let (mut last, cached) = self.releases(cache.as_ref()).await?;
 
-        if let Paging::Next(_, data) = version {
-            debug!(latest_versions = ?data, "Version from GitHub Release");
-            self.download(data, current_version).await
+        let report = if !last.releases.is_empty() {
+            debug!(latest_versions = ?last.releases, cached = cached, "Version from GitHub Release");
+            self.download(&last.releases, current_version, cached)
+                .await?
         } else {
             debug!("No versions available in Github Release");
-            Ok(())
+            CheckReport {
+                output: self.output,
+                current: current_version,
+                latest: None,
+                update_available: false,
+                action: Action::None,
+                cached,
+                install: None,
+            }
+        };
+
+        if let Some(cache) = &cache {
+            record(&mut last, &report).await;
+
+            if let Err(err) = last.save(&cache.path) {
+                warn!(path = %cache.path.display(), error = %err, "Failed to record the last check");
+            }
         }
+
+        Ok(report)
     }
 }
 
@@ -192,4 +372,35 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap());
     }
+
+    #[test]
+    fn test_check_report_json() {
+        let report = CheckReport {
+            output: "/home/user/bin/rust-analyzer".into(),
+            current: Some(Version {
+                date_version: "2022-08-15".into(),
+                semantic_version: "0.3.1000-standalone".into(),
+            }),
+            latest: Some("2022-08-22".into()),
+            update_available: true,
+            action: Action::Downloaded,
+            cached: false,
+            install: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "output": "/home/user/bin/rust-analyzer",
+                "current": {
+                    "date_version": "2022-08-15",
+                    "semantic_version": "0.3.1000-standalone"
+                },
+                "latest": "2022-08-22",
+                "update_available": true,
+                "action": "downloaded",
+                "cached": false
+            })
+        );
+    }
 }
@@ -1,11 +1,27 @@
+use std::io::ErrorKind;
+
+use serde::Serialize;
 use thiserror::Error as ThisError;
 
+use crate::config::Error as ConfigError;
 use rust_analyzer_downloader::rust_analyzer::version::Error as CurrentVersionError;
 use rust_analyzer_downloader::services::downloader::Error as DownloaderError;
+use rust_analyzer_downloader::services::transport::Error as TransportError;
 use rust_analyzer_downloader::services::versions::Error as VersionsError;
 
+pub(crate) const EXIT_SUCCESS: i32 = 0;
+pub(crate) const EXIT_FAILURE: i32 = 1;
+pub(crate) const EXIT_NETWORK: i32 = 3;
+pub(crate) const EXIT_IO: i32 = 4;
+pub(crate) const EXIT_PARSE: i32 = 5;
+pub(crate) const EXIT_VERSION_COMMAND: i32 = 6;
+/// `check` found a newer release and did not download it
+pub(crate) const EXIT_UPDATE_AVAILABLE: i32 = 10;
+/// Interrupted with Ctrl-C, like a shell reports SIGINT
+pub(crate) const EXIT_INTERRUPTED: i32 = 130;
+
 #[derive(Debug, ThisError)]
-pub(super) enum Errors {
+pub(crate) enum Errors {
     #[error(transparent)]
     Download(#[from] DownloaderError),
     #[error(transparent)]
@@ -15,9 +31,192 @@ pub(super) enum Errors {
 
     #[error(transparent)]
     ParseDate(#[from] time::error::Parse),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error(transparent)]
+    Client(#[from] reqwest::Error),
+
+    #[error("{0} doctor check(s) failed")]
+    Doctor(usize),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("self-update failed: {0}")]
+    SelfUpdate(String),
+
+    #[error("schedule: {0}")]
+    Schedule(String),
+
+    #[error("interrupted")]
+    Interrupted,
+}
+
+fn network_exit_code(err: &TransportError) -> i32 {
+    match err {
+        TransportError::Reqwest(err) if err.is_decode() => EXIT_PARSE,
+        _ => EXIT_NETWORK,
+    }
+}
+
+impl Errors {
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            Errors::Download(DownloaderError::Network { source, .. })
+            | Errors::GetVersions(VersionsError::Network { source, .. }) => {
+                network_exit_code(source)
+            }
+            Errors::Download(DownloaderError::Client(_))
+            | Errors::GetVersions(VersionsError::Client(_))
+            | Errors::Client(_) => EXIT_NETWORK,
+            Errors::Download(DownloaderError::Cancelled)
+            | Errors::GetVersions(VersionsError::Cancelled)
+            | Errors::Interrupted => EXIT_INTERRUPTED,
+            Errors::Download(DownloaderError::File { .. })
+            | Errors::Download(DownloaderError::Runtime(_))
+            | Errors::GetVersions(VersionsError::Runtime(_))
+            | Errors::CurrentVersion(CurrentVersionError::Io { .. })
+            | Errors::Config(ConfigError::Io(_, _))
+            | Errors::Io(_) => EXIT_IO,
+            Errors::CurrentVersion(CurrentVersionError::Parse(_))
+            | Errors::GetVersions(VersionsError::Parse { .. })
+            | Errors::ParseDate(_)
+            | Errors::Json(_)
+            | Errors::Config(ConfigError::Parse(_, _))
+            | Errors::Config(ConfigError::InvalidValue(_, _)) => EXIT_PARSE,
+            Errors::CurrentVersion(CurrentVersionError::Command(_))
+            | Errors::CurrentVersion(CurrentVersionError::Timeout(_, _)) => EXIT_VERSION_COMMAND,
+            Errors::Download(DownloaderError::Locked(_))
+            | Errors::Config(_)
+            | Errors::Doctor(_)
+            | Errors::SelfUpdate(_)
+            | Errors::Schedule(_) => EXIT_FAILURE,
+        }
+    }
+
+    /// What the user can do about the error, printed below it.
+    pub(crate) fn hint(&self) -> Option<String> {
+        let hint = match self {
+            Errors::Download(DownloaderError::File { source, .. })
+                if source.kind() == ErrorKind::PermissionDenied =>
+            {
+                "choose a writable location with --output, or run rad with sudo".into()
+            }
+            Errors::Download(DownloaderError::Network {
+                version,
+                source: TransportError::Status(404),
+                ..
+            }) => format!(
+                "release {} has no asset for your target, check the tag with `rad get-versions` or pass --target",
+                version
+            ),
+            Errors::GetVersions(VersionsError::Network {
+                source: TransportError::Status(404),
+                ..
+            }) => "the releases API was not found, check --api-url".into(),
+            Errors::Download(DownloaderError::Network { source, .. })
+            | Errors::GetVersions(VersionsError::Network { source, .. }) => match source {
+                TransportError::Status(403) | TransportError::Status(429) => {
+                    "GitHub rate limited the request, set GITHUB_TOKEN to raise the limit".into()
+                }
+                source if source.is_retryable() => {
+                    "check your network connection and proxy settings, or raise --retries and --timeout".into()
+                }
+                _ => return None,
+            },
+            Errors::Download(DownloaderError::Locked(_)) => {
+                "wait for the other rad to finish, eg. a scheduled check".into()
+            }
+            Errors::Config(ConfigError::Paths(_)) => {
+                "set HOME or the XDG directory variables, or pass --root".into()
+            }
+            _ => return None,
+        };
+
+        Some(hint)
+    }
+}
+
+/// Result of a command, printed as a JSON document with `--output-format json`
+pub(super) trait Report: Serialize + Send {
+    /// Prints the report in human readable form, most commands log as they go
+    fn print(&self) {}
+
+    /// Error to fail with once the report has been printed
+    fn error(&self) -> Option<Errors> {
+        None
+    }
+
+    /// Process exit code when the command succeeded
+    fn exit_code(&self) -> i32 {
+        EXIT_SUCCESS
+    }
 }
 
 #[async_trait::async_trait]
 pub(super) trait Command {
-    async fn execute(self) -> Result<(), Errors>;
+    type Output: Report;
+
+    async fn execute(self) -> Result<Self::Output, Errors>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Error as IoError;
+    use std::path::PathBuf;
+
+    fn file_error(kind: ErrorKind) -> Errors {
+        Errors::Download(DownloaderError::File {
+            operation: "install",
+            path: PathBuf::from("/usr/local/bin/rust-analyzer"),
+            source: IoError::from(kind),
+        })
+    }
+
+    fn network_error(status: u16) -> Errors {
+        Errors::Download(DownloaderError::Network {
+            url: "https://example.com/2022-08-22/rust-analyzer.gz".into(),
+            version: "2022-08-22".into(),
+            source: TransportError::Status(status),
+        })
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let io = file_error(ErrorKind::NotFound);
+        let parse = Errors::CurrentVersion(CurrentVersionError::Parse("bad".into()));
+        let command = Errors::CurrentVersion(CurrentVersionError::Command("rust-analyzer".into()));
+
+        assert_eq!(io.exit_code(), EXIT_IO);
+        assert_eq!(parse.exit_code(), EXIT_PARSE);
+        assert_eq!(command.exit_code(), EXIT_VERSION_COMMAND);
+        assert_eq!(Errors::Doctor(1).exit_code(), EXIT_FAILURE);
+        assert_eq!(
+            Errors::Download(DownloaderError::Cancelled).exit_code(),
+            EXIT_INTERRUPTED
+        );
+    }
+
+    #[test]
+    fn test_hints() {
+        assert!(file_error(ErrorKind::PermissionDenied)
+            .hint()
+            .unwrap()
+            .contains("--output"));
+        assert!(network_error(404).hint().unwrap().contains("2022-08-22"));
+        assert!(network_error(403).hint().unwrap().contains("GITHUB_TOKEN"));
+        assert!(network_error(503).hint().unwrap().contains("network"));
+        assert_eq!(file_error(ErrorKind::NotFound).hint(), None);
+        assert_eq!(
+            Errors::Io(IoError::from(ErrorKind::PermissionDenied)).hint(),
+            None
+        );
+        assert_eq!(Errors::Interrupted.hint(), None);
+    }
 }
@@ -0,0 +1,380 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tokio::process::Command as Process;
+use tokio::time::timeout;
+use tracing::debug;
+
+use super::command::{Command, Errors, Report};
+use rust_analyzer_downloader::fs::{is_in_path, same_file};
+use rust_analyzer_downloader::paths::Paths;
+use rust_analyzer_downloader::rust_analyzer::discovery::{discover, Source};
+use rust_analyzer_downloader::rust_analyzer::version::{inspect, DEFAULT_TIMEOUT};
+use rust_analyzer_downloader::services::versions::Versions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Pass => write!(f, "PASS"),
+            Status::Warn => write!(f, "WARN"),
+            Status::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    message: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.name, self.message)?;
+
+        if let Some(fix) = &self.fix {
+            write!(f, "\n       fix: {}", fix)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct DoctorCommand {
+    output: String,
+    paths: Paths,
+    versions: Versions,
+}
+
+impl DoctorCommand {
+    pub(super) fn new(output: String, paths: Paths, versions: Versions) -> Self {
+        Self {
+            output,
+            paths,
+            versions,
+        }
+    }
+
+    fn output_dir(&self) -> Check {
+        const NAME: &str = "output directory";
+
+        let dir = match Path::new(&self.output).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => {
+                return Check::warn(
+                    NAME,
+                    format!("{} has no parent directory", self.output),
+                    "pass an absolute path with --output or RAD_OUTPUT_PATH",
+                )
+            }
+        };
+
+        if !dir.is_dir() {
+            Check::fail(
+                NAME,
+                format!("{} does not exist", dir.display()),
+                format!("mkdir -p {}", dir.display()),
+            )
+        } else if !is_in_path(dir) {
+            Check::fail(
+                NAME,
+                format!("{} is not on PATH", dir.display()),
+                format!(
+                    "add 'export PATH=\"{}:$PATH\"' to your shell rc file",
+                    dir.display()
+                ),
+            )
+        } else {
+            Check::pass(NAME, format!("{} exists and is on PATH", dir.display()))
+        }
+    }
+
+    async fn installed_binary(&self) -> Check {
+        const NAME: &str = "installed binary";
+
+        match inspect(&self.output, DEFAULT_TIMEOUT).await {
+            Ok(version) => Check::pass(
+                NAME,
+                format!(
+                    "{} is {} ({})",
+                    self.output, version.semantic_version, version.date_version
+                ),
+            ),
+            Err(err) => Check::fail(
+                NAME,
+                format!("{}: {}", self.output, err),
+                format!("rad download nightly --output {}", self.output),
+            ),
+        }
+    }
+
+    async fn shadowing(&self) -> Check {
+        const NAME: &str = "PATH resolution";
+
+        let installations = discover(DEFAULT_TIMEOUT).await;
+        let active = match installations
+            .iter()
+            .find(|installation| installation.active)
+        {
+            Some(active) => active,
+            None => {
+                return Check::fail(
+                    NAME,
+                    "no rust-analyzer found on PATH",
+                    "add the output directory to PATH",
+                )
+            }
+        };
+
+        if active.source == Source::RustupProxy && !rustup_has_component().await {
+            return Check::fail(
+                NAME,
+                format!(
+                    "{} is a rustup proxy but the active toolchain has no rust-analyzer component",
+                    active.path.display()
+                ),
+                format!(
+                    "rustup component add rust-analyzer, or put {} before {} in PATH",
+                    parent_display(Path::new(&self.output)),
+                    parent_display(&active.path),
+                ),
+            );
+        }
+
+        if !same_file(&active.path, Path::new(&self.output)) {
+            return Check::warn(
+                NAME,
+                format!(
+                    "PATH resolves rust-analyzer to {} ({}), not {}",
+                    active.path.display(),
+                    active.source,
+                    self.output
+                ),
+                format!(
+                    "put {} before {} in PATH",
+                    parent_display(Path::new(&self.output)),
+                    parent_display(&active.path),
+                ),
+            );
+        }
+
+        Check::pass(
+            NAME,
+            format!("rust-analyzer resolves to {}", active.path.display()),
+        )
+    }
+
+    async fn cache_dir(&self) -> Check {
+        const NAME: &str = "cache directory";
+
+        let cache_dir = match self.paths.cache_dir() {
+            Ok(dir) => dir,
+            Err(err) => {
+                return Check::fail(
+                    NAME,
+                    err.to_string(),
+                    "set XDG_CACHE_HOME or HOME, or pass --root",
+                )
+            }
+        };
+
+        let probe = cache_dir.join(format!(".rad-doctor-{}", std::process::id()));
+        let written = match tokio::fs::create_dir_all(&cache_dir).await {
+            Ok(_) => tokio::fs::write(&probe, b"").await,
+            Err(err) => Err(err),
+        };
+
+        match written {
+            Ok(_) => {
+                let _ = tokio::fs::remove_file(&probe).await;
+                Check::pass(NAME, format!("{} is writable", cache_dir.display()))
+            }
+            Err(err) => Check::fail(
+                NAME,
+                format!("{} is not writable: {}", cache_dir.display(), err),
+                format!("mkdir -p {0} && chmod u+w {0}", cache_dir.display()),
+            ),
+        }
+    }
+
+    async fn github(&self) -> Check {
+        const NAME: &str = "GitHub";
+
+        match self.versions.get(1, 1).await {
+            Ok(_) => Check::pass(NAME, "GitHub Releases API is reachable"),
+            Err(err) => Check::fail(
+                NAME,
+                format!("GitHub Releases API is not reachable: {}", err),
+                "check your network connection and proxy settings",
+            ),
+        }
+    }
+}
+
+async fn rust_src() -> Check {
+    const NAME: &str = "rust-src";
+
+    let sysroot = match run("rustc", &["--print", "sysroot"]).await {
+        Some(sysroot) => PathBuf::from(sysroot.trim()),
+        None => {
+            return Check::warn(
+                NAME,
+                "rustc not found, cannot locate the active toolchain",
+                "install Rust with rustup from https://rustup.rs",
+            )
+        }
+    };
+
+    let library = sysroot
+        .join("lib")
+        .join("rustlib")
+        .join("src")
+        .join("rust")
+        .join("library");
+
+    if library.is_dir() {
+        Check::pass(
+            NAME,
+            format!("installed for toolchain at {}", sysroot.display()),
+        )
+    } else {
+        Check::fail(
+            NAME,
+            format!("missing for toolchain at {}", sysroot.display()),
+            "rustup component add rust-src",
+        )
+    }
+}
+
+async fn rustup_has_component() -> bool {
+    run("rustup", &["which", "rust-analyzer"]).await.is_some()
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Process::new(program).args(args).kill_on_drop(true).output();
+
+    match timeout(DEFAULT_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        result => {
+            debug!(program, ?args, ?result, "Command failed");
+            None
+        }
+    }
+}
+
+fn parent_display(path: &Path) -> String {
+    path.parent()
+        .map(|parent| parent.display().to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct DoctorReport {
+    checks: Vec<Check>,
+}
+
+impl Report for DoctorReport {
+    fn print(&self) {
+        self.checks.iter().for_each(|check| println!("{}", check));
+    }
+
+    fn error(&self) -> Option<Errors> {
+        let failed = self
+            .checks
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .count();
+
+        (failed > 0).then_some(Errors::Doctor(failed))
+    }
+}
+
+#[async_trait::async_trait]
+impl Command for DoctorCommand {
+    type Output = DoctorReport;
+
+    async fn execute(self) -> Result<DoctorReport, Errors> {
+        let checks = vec![
+            self.output_dir(),
+            self.installed_binary().await,
+            rust_src().await,
+            self.shadowing().await,
+            self.cache_dir().await,
+            self.github().await,
+        ];
+
+        Ok(DoctorReport { checks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_display_with_fix() {
+        let check = Check::fail("rust-src", "missing", "rustup component add rust-src");
+
+        assert_eq!(
+            check.to_string(),
+            "[FAIL] rust-src: missing\n       fix: rustup component add rust-src"
+        );
+    }
+
+    #[test]
+    fn test_output_dir_missing() {
+        let command = DoctorCommand::new(
+            "/does-not-exist/bin/rust-analyzer".into(),
+            Paths::new(),
+            Versions::new(reqwest::Client::new()),
+        );
+
+        let check = command.output_dir();
+
+        assert_eq!(check.status, Status::Fail);
+        assert_eq!(check.fix.as_deref(), Some("mkdir -p /does-not-exist/bin"));
+    }
+}
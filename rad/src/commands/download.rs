@@ -1,29 +1,76 @@
+use serde::Serialize;
 use tracing::debug;
 
-use super::command::{Command, Errors};
-use rust_analyzer_downloader::services::downloader::Downloader;
+use super::command::{Command, Errors, Report};
+use rust_analyzer_downloader::services::downloader::{Downloader, InstallReport};
 use std::fmt::Debug;
 
+#[derive(Debug, Serialize)]
+pub(super) struct DownloadReport {
+    version: String,
+    #[serde(flatten)]
+    install: InstallReport,
+}
+
 #[derive(Debug)]
 pub(super) struct DownloadCommand {
     version: String,
     output: String,
     downloader: Downloader,
+    /// Allows asking on the terminal before changing the shell configuration
+    interactive: bool,
 }
 
 impl DownloadCommand {
-    pub(super) fn new(version: String, output: String, downloader: Downloader) -> Self {
+    pub(super) fn new(
+        version: String,
+        output: String,
+        downloader: Downloader,
+        interactive: bool,
+    ) -> Self {
         Self {
             version,
             output,
             downloader,
+            interactive,
         }
     }
 }
 
+fn megabytes(bytes: f64) -> f64 {
+    bytes / (1024.0 * 1024.0)
+}
+
+/// Prints what was downloaded and where it was installed.
+pub(super) fn print_install(install: &InstallReport) {
+    println!("Installed:     {}", install.path.display());
+    println!("From:          {}", install.transfer.url);
+    println!(
+        "Size:          {:.2} MB compressed, {:.2} MB decompressed",
+        megabytes(install.transfer.compressed_size as f64),
+        megabytes(install.transfer.decompressed_size as f64)
+    );
+    println!("SHA-256:       {}", install.transfer.sha256);
+    println!(
+        "Download:      {:.2?} ({:.2} MB/s)",
+        install.transfer.download_time,
+        megabytes(install.transfer.throughput())
+    );
+    println!("Decompression: {:.2?}", install.transfer.decompress_time);
+}
+
+impl Report for DownloadReport {
+    fn print(&self) {
+        println!("Version:       {}", self.version);
+        print_install(&self.install);
+    }
+}
+
 #[async_trait::async_trait]
 impl Command for DownloadCommand {
-    async fn execute(self) -> Result<(), Errors> {
+    type Output = DownloadReport;
+
+    async fn execute(self) -> Result<DownloadReport, Errors> {
         debug!(
             version = &self.version,
             output = &self.output,
@@ -32,15 +79,65 @@ impl Command for DownloadCommand {
 
         let result = self.downloader.download(&self.version, &self.output).await;
 
-        debug!(
-            version = &self.version,
-            output = &self.output,
-            "Version successfully downloaded from GitHub"
-        );
-
         match result {
-            Ok(_) => Ok(()),
+            Ok(install) => {
+                debug!(
+                    version = &self.version,
+                    output = &self.output,
+                    sha256 = install.transfer.sha256.as_str(),
+                    "Version successfully downloaded from GitHub"
+                );
+
+                crate::shell::ensure_in_path(&self.output, self.interactive).await;
+                Ok(DownloadReport {
+                    version: self.version,
+                    install,
+                })
+            }
             Err(err) => Err(Errors::Download(err)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_analyzer_downloader::services::downloader::TransferReport;
+    use std::time::Duration;
+
+    #[test]
+    fn test_download_report_json() {
+        let report = DownloadReport {
+            version: "2022-08-22".into(),
+            install: InstallReport {
+                transfer: TransferReport {
+                    url: "https://example.com/2022-08-22/rust-analyzer-x86_64-unknown-linux-gnu.gz"
+                        .into(),
+                    asset: "rust-analyzer-x86_64-unknown-linux-gnu.gz".into(),
+                    compressed_size: 2048,
+                    decompressed_size: 8192,
+                    sha256: "ab".repeat(32),
+                    download_time: Duration::from_millis(500),
+                    decompress_time: Duration::from_millis(250),
+                },
+                path: "/home/user/bin/rust-analyzer".into(),
+            },
+        };
+
+        assert_eq!(report.install.transfer.throughput(), 4096.0);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "version": "2022-08-22",
+                "url": "https://example.com/2022-08-22/rust-analyzer-x86_64-unknown-linux-gnu.gz",
+                "asset": "rust-analyzer-x86_64-unknown-linux-gnu.gz",
+                "compressed_size": 2048,
+                "decompressed_size": 8192,
+                "sha256": "ab".repeat(32),
+                "download_time": 0.5,
+                "decompress_time": 0.25,
+                "path": "/home/user/bin/rust-analyzer"
+            })
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::io::Write;
+
+use clap::Command;
+use clap_complete::Shell;
+
+/// Name of the installed binary, completions are registered for it.
+pub(super) const BIN_NAME: &str = "rad";
+
+/// Writes the completion script for `shell`.
+pub(super) fn completions(mut command: Command, shell: Shell, out: &mut dyn Write) {
+    clap_complete::generate(shell, &mut command, BIN_NAME, out);
+}
+
+/// Writes the man page in roff format, every section is derived from the clap definitions.
+pub(super) fn manpage(command: Command, out: &mut dyn Write) -> std::io::Result<()> {
+    clap_mangen::Man::new(command.name(BIN_NAME)).render(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_manpage_lists_subcommands_and_options() {
+        let mut out = Vec::new();
+        manpage(Cli::command(), &mut out).unwrap();
+        let manpage = String::from_utf8(out).unwrap();
+
+        assert!(manpage.contains(".TH rad 1 "));
+        assert!(manpage.contains("rad\\-check(1)"));
+        assert!(manpage.contains("rad\\-completions(1)"));
+        assert!(manpage.contains("\\fB\\-\\-output\\-format\\fR"));
+        assert!(manpage.contains("[default: human]"));
+    }
+
+    #[test]
+    fn test_completions_for_every_subcommand() {
+        let mut out = Vec::new();
+        completions(Cli::command(), Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+
+        for subcommand in Cli::command().get_subcommands() {
+            assert!(script.contains(subcommand.get_name()));
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use super::{command::Errors, Command};
-use rust_analyzer_downloader::services::versions::{Paging, Versions};
+use super::command::{Command, Errors, Report};
+use rust_analyzer_downloader::services::versions::{Paging, ReleasesJsonResponse, Versions};
+use serde::Serialize;
 use tracing::info;
 
+#[derive(Debug, Serialize)]
+pub(super) struct Releases {
+    releases: Vec<ReleasesJsonResponse>,
+}
+
 #[derive(Debug)]
 pub(super) struct GetVersionsCommand {
     versions: Versions,
@@ -14,9 +20,13 @@ impl GetVersionsCommand {
     }
 }
 
+impl Report for Releases {}
+
 #[async_trait::async_trait]
 impl Command for GetVersionsCommand {
-    async fn execute(self) -> Result<(), Errors> {
+    type Output = Releases;
+
+    async fn execute(self) -> Result<Releases, Errors> {
         let result = self.versions.get(1, self.per_page).await;
 
         match result {
@@ -25,10 +35,12 @@ impl Command for GetVersionsCommand {
                     info!(version = release.tag_name, prerelease = release.prerelease);
                 });
 
-                Ok(())
+                Ok(Releases { releases: data })
             }
             Err(err) => Err(Errors::GetVersions(err)),
-            _ => Ok(()),
+            _ => Ok(Releases {
+                releases: Vec::new(),
+            }),
         }
     }
 }
@@ -1,19 +1,49 @@
-use std::env::VarError;
+use std::future::Future;
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
 
-use clap::{Parser, Subcommand};
-use command::Command;
-use directories::BaseDirs;
-use tracing::{debug, error};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use command::{Command, Report, EXIT_SUCCESS};
+use reqwest::header::HeaderValue;
+use tracing::{debug, warn};
 
-use self::{check::CheckCommand, download::DownloadCommand, get_versions::GetVersionsCommand};
-use rust_analyzer_downloader::services::downloader::Downloader;
-use rust_analyzer_downloader::services::versions::Versions;
+use self::{
+    check::Cache,
+    check::CheckCommand,
+    doctor::DoctorCommand,
+    download::DownloadCommand,
+    get_versions::GetVersionsCommand,
+    prompt::PromptCommand,
+    schedule::ScheduleAction,
+    schedule::{JobOptions, ScheduleCommand},
+    self_update::SelfUpdateCommand,
+    which::WhichCommand,
+};
+use crate::auth::AuthTransport;
+use crate::config::{Channel, Config, Error as ConfigError, Settings};
+use crate::logging::LogArgs;
+use rust_analyzer_downloader::paths::Paths;
+use rust_analyzer_downloader::rust_analyzer::version::which;
+use rust_analyzer_downloader::services::cancel::CancellationToken;
+use rust_analyzer_downloader::services::downloader::{Downloader, DownloaderBuilder};
+use rust_analyzer_downloader::services::transport::ReqwestTransport;
+use rust_analyzer_downloader::services::versions::{Versions, VersionsBuilder};
+use rust_analyzer_downloader::services::RetryPolicy;
+
+pub(crate) use command::{Errors, EXIT_IO};
 
 mod check;
 mod command;
+mod doctor;
 mod download;
+mod generate;
 mod get_versions;
+mod prompt;
+mod schedule;
+mod self_update;
+mod which;
 
 #[derive(Debug, Subcommand)]
 enum Commands {
@@ -21,103 +51,455 @@ enum Commands {
         #[clap(required = false, value_parser)]
         version: String,
 
-        #[clap(short, long, required = false, value_parser, default_value_t=get_default_output_path())]
-        output: String,
+        #[clap(short, long, required = false, value_parser)]
+        output: Option<String>,
     },
     GetVersions {
         #[clap(short, long, required = false, value_parser, default_value_t = 3)]
         per_page: u32,
     },
     Check {
-        #[clap(short, long, required = false, value_parser, default_value_t=get_default_output_path())]
-        output: String,
+        #[clap(short, long, required = false, value_parser)]
+        output: Option<String>,
         #[clap(short, long, required = false, value_parser, default_value_t = false)]
         nightly: bool,
         #[clap(short, long, required = false, value_parser, default_value_t = false)]
         download: bool,
+        /// Reuses the last check when it is more recent than this, eg. 6h
+        #[clap(long, value_parser = crate::state::parse_interval)]
+        min_interval: Option<Duration>,
+        /// Always asks the releases API, ignoring --min-interval
+        #[clap(long, required = false, value_parser, default_value_t = false)]
+        force: bool,
+    },
+    /// Shows which rust-analyzer runs from PATH
+    Which {
+        /// Lists every installation found on PATH and in well-known locations
+        #[clap(short, long, required = false, value_parser, default_value_t = false)]
+        all: bool,
+    },
+    /// Checks the environment for common rust-analyzer setup problems
+    Doctor {
+        #[clap(short, long, required = false, value_parser)]
+        output: Option<String>,
+    },
+    /// Prints the shell completion script to stdout
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+    /// Prints the man page in roff format to stdout
+    Manpage,
+    /// Replaces this executable with the latest rad release
+    SelfUpdate {
+        /// Reinstalls the latest release even when it is not newer
+        #[clap(short, long, required = false, value_parser, default_value_t = false)]
+        force: bool,
+
+        /// URL of the releases API for rad itself
+        #[clap(long, value_parser, default_value = self_update::SELF_RELEASE_API_URL)]
+        release_api_url: String,
+
+        /// Base URL for rad release downloads
+        #[clap(long, value_parser, default_value = self_update::SELF_DOWNLOAD_URL)]
+        release_download_url: String,
+    },
+    /// Prints a marker when the last check found an update, for shell prompts
+    Prompt {
+        /// Binary to answer for, defaults to the one the last check looked at
+        #[clap(short, long, required = false, value_parser)]
+        output: Option<String>,
+
+        /// Text printed when an update is pending
+        #[clap(long, value_parser, default_value = prompt::DEFAULT_MARKER)]
+        marker: String,
     },
+    /// Manages a periodic `rad check --download`
+    Schedule {
+        #[clap(subcommand)]
+        action: ScheduleAction,
+    },
+}
+
+impl Commands {
+    /// Commands that clean up after themselves on Ctrl-C, the others keep the
+    /// default signal handling and exit right away.
+    fn is_cancellable(&self) -> bool {
+        matches!(
+            self,
+            Commands::Download { .. }
+                | Commands::GetVersions { .. }
+                | Commands::Check { .. }
+                | Commands::Doctor { .. }
+                | Commands::SelfUpdate { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
 }
 
 #[derive(Debug, Parser)]
-#[clap(name = "rust-analyzer-downloader",about = "Downloads and gets versions for Rust Analyzer", long_about = None)]
+#[clap(name = "rust-analyzer-downloader", version, about = "Downloads and gets versions for Rust Analyzer", long_about = None)]
 pub struct Cli {
+    /// Format of the command result printed on stdout
+    #[clap(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    pub output_format: OutputFormat,
+
+    #[clap(flatten)]
+    pub logging: LogArgs,
+
+    /// Profile from the config file to use
+    #[clap(long, global = true, value_parser)]
+    profile: Option<String>,
+
+    /// Config file, defaults to $XDG_CONFIG_HOME/rad/config.toml
+    #[clap(long, global = true, value_parser)]
+    config: Option<PathBuf>,
+
+    /// Keeps config, state, cache and the default install below this directory
+    #[clap(long, global = true, value_parser)]
+    root: Option<PathBuf>,
+
+    /// Base URL for release downloads, eg. a mirror of GitHub Releases
+    #[clap(long, global = true, value_parser)]
+    download_url: Option<String>,
+
+    /// URL of the releases API, eg. a mirror of the GitHub Releases API
+    #[clap(long, global = true, value_parser)]
+    api_url: Option<String>,
+
+    /// Number of retries for failed requests
+    #[clap(long, global = true, value_parser)]
+    retries: Option<u32>,
+
+    /// Request timeout in seconds
+    #[clap(long, global = true, value_parser)]
+    timeout: Option<u64>,
+
+    /// Target triple of the release asset to download
+    #[clap(long, global = true, value_parser)]
+    target: Option<String>,
+
     #[clap(subcommand)]
     commands: Commands,
 }
 
-fn default_user_output_path() -> String {
-    let base_dirs = BaseDirs::new().unwrap();
-    let home_dir = base_dirs.home_dir();
+impl Cli {
+    fn settings(&self) -> Settings {
+        let (output, nightly) = match &self.commands {
+            Commands::Download { output, .. } | Commands::Doctor { output } => {
+                (output.clone(), false)
+            }
+            Commands::Check {
+                output, nightly, ..
+            } => (output.clone(), *nightly),
+            _ => (None, false),
+        };
+
+        Settings {
+            output,
+            channel: nightly.then_some(Channel::Nightly),
+            download_url: self.download_url.clone(),
+            api_url: self.api_url.clone(),
+            token: None,
+            retries: self.retries,
+            timeout: self.timeout,
+            target: self.target.clone(),
+        }
+    }
+}
+
+type HttpTransport = AuthTransport<ReqwestTransport>;
 
-    let mut buf = PathBuf::new();
+/// Resolves the token here rather than in `Config`, so a failing token command
+/// only breaks the commands that send requests.
+fn transport(config: &Config) -> Result<HttpTransport, Errors> {
+    let token = config
+        .token
+        .as_ref()
+        .map(|token| token.resolve())
+        .transpose()?;
 
-    buf.push(home_dir);
-    buf.push("bin");
-    #[cfg(target_family = "windows")]
-    buf.push("rust-analyzer.exe");
-    #[cfg(target_family = "unix")]
-    buf.push("rust-analyzer");
+    if let Some(token) = &token {
+        HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| ConfigError::Token("token contains invalid characters".into()))?;
+    }
+
+    let client = reqwest::ClientBuilder::new().build()?;
 
-    buf.as_path().to_string_lossy().into()
+    Ok(AuthTransport::new(
+        ReqwestTransport::new(client),
+        &config.api_url,
+        token,
+    ))
 }
 
-fn get_default_output_path() -> String {
-    let env = std::env::var("RAD_OUTPUT_PATH");
+fn retry_policy(config: &Config) -> RetryPolicy {
+    RetryPolicy {
+        retries: config.retries,
+        ..RetryPolicy::default()
+    }
+}
 
-    match env {
-        Ok(path) => path,
-        Err(VarError::NotUnicode(os_str)) => {
-            error!("RAD_OUTPUT_PATH is not unicode: {:?}", os_str);
+fn downloader(
+    transport: HttpTransport,
+    config: &Config,
+    paths: &Paths,
+    cancel: &CancellationToken,
+) -> DownloaderBuilder {
+    let builder = Downloader::builder()
+        .transport(transport)
+        .paths(paths.clone())
+        .cancellation_token(cancel.clone())
+        .base_url(config.download_url.as_str())
+        .target(config.target.as_str())
+        .retry_policy(retry_policy(config));
 
-            default_user_output_path()
-        }
-        Err(VarError::NotPresent) => {
-            let path = default_user_output_path();
-            debug!(
-                path = path.as_str(),
-                "RAD_OUTPUT_PATH not present, using default path"
-            );
-
-            path
+    match config.timeout {
+        Some(timeout) => builder.timeout(timeout),
+        None => builder,
+    }
+}
+
+fn versions(
+    transport: HttpTransport,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> VersionsBuilder {
+    let builder = Versions::builder()
+        .transport(transport)
+        .cancellation_token(cancel.clone())
+        .api_url(config.api_url.as_str())
+        .retry_policy(retry_policy(config));
+
+    match config.timeout {
+        Some(timeout) => builder.timeout(timeout),
+        None => builder,
+    }
+}
+
+fn print_error(format: OutputFormat, err: &Errors) {
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::json!({ "error": err.to_string(), "hint": err.hint() })
+        );
+    }
+}
+
+async fn run<C: Command>(command: C, format: OutputFormat) -> Result<i32, Errors> {
+    let report = match command.execute().await {
+        Ok(report) => report,
+        Err(err) => {
+            print_error(format, &err);
+            return Err(err);
         }
+    };
+
+    match format {
+        OutputFormat::Human => report.print(),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    match report.error() {
+        Some(err) => Err(err),
+        None => Ok(report.exit_code()),
     }
 }
 
+/// Runs the command, returns the process exit code on success
 // #[tracing::instrument]
-pub async fn execute() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Cli::parse();
-    let client = reqwest::ClientBuilder::new().build()?;
+pub async fn execute(args: Cli) -> Result<i32, Errors> {
+    let format = args.output_format;
+    let cancellable = args.commands.is_cancellable();
+    let cancel = CancellationToken::new();
+
+    let future = match prepare(args, &cancel) {
+        Ok(future) => future,
+        Err(err) => {
+            print_error(format, &err);
+            return Err(err);
+        }
+    };
+
+    if !cancellable {
+        return future.await;
+    }
+
+    tokio::select! {
+        result = future => result,
+        _ = interrupted(&cancel) => {
+            print_error(format, &Errors::Interrupted);
+            Err(Errors::Interrupted)
+        }
+    }
+}
+
+/// Cancels downloads and release lookups on the first Ctrl-C so they clean up
+/// after themselves, completes on the second one to exit right away.
+async fn interrupted(cancel: &CancellationToken) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return std::future::pending().await;
+    }
+    warn!("Interrupted, cleaning up, press Ctrl-C again to exit immediately");
+    cancel.cancel();
+
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending().await
+    }
+}
+
+type CommandFuture = Pin<Box<dyn Future<Output = Result<i32, Errors>>>>;
+
+fn prepare(args: Cli, cancel: &CancellationToken) -> Result<CommandFuture, Errors> {
+    let format = args.output_format;
+    let paths = match args.root.clone() {
+        Some(root) => Paths::new().with_root(root),
+        None => Paths::new(),
+    };
+
+    // Only commands that talk to the network read the config file and the token,
+    // `prompt` in particular answers from local state on every shell prompt
+    let settings = args.settings();
+    let load = |paths: &Paths| -> Result<(Config, HttpTransport), Errors> {
+        let config = Config::load(settings, args.profile.clone(), args.config.clone(), paths)?;
+        debug!(config = ?config, "Resolved configuration");
+        let transport = transport(&config)?;
+
+        Ok((config, transport))
+    };
+
+    let future: CommandFuture = match args.commands {
+        Commands::Completions { shell } => {
+            generate::completions(Cli::command(), shell, &mut std::io::stdout());
+            Box::pin(async { Ok(EXIT_SUCCESS) })
+        }
+        Commands::Manpage => {
+            generate::manpage(Cli::command(), &mut std::io::stdout())?;
+            Box::pin(async { Ok(EXIT_SUCCESS) })
+        }
+        Commands::Prompt { output, marker } => Box::pin(run(
+            PromptCommand::new(output, crate::state::default_path(&paths), marker),
+            format,
+        )),
+        Commands::Download { version, .. } => {
+            let (config, transport) = load(&paths)?;
 
-    let future = match args.commands {
-        Commands::Download { version, output } => {
-            Box::pin(DownloadCommand::new(version, output, Downloader::new(client)).execute())
+            Box::pin(run(
+                DownloadCommand::new(
+                    version,
+                    config.output(&paths)?,
+                    downloader(transport, &config, &paths, cancel).build()?,
+                    format == OutputFormat::Human,
+                ),
+                format,
+            ))
         }
         Commands::GetVersions { per_page } => {
+            let (config, transport) = load(&paths)?;
+
             debug!("Fetching versions from GitHub Releases API");
-            let result =
-                Box::pin(GetVersionsCommand::new(Versions::new(client), per_page).execute());
+            let result = Box::pin(run(
+                GetVersionsCommand::new(versions(transport, &config, cancel).build()?, per_page),
+                format,
+            ));
             debug!("Fetching versions completed from GitHub Releases API");
 
             result
         }
         Commands::Check {
-            output,
-            nightly,
             download,
-        } => Box::pin(
-            CheckCommand::new(
-                output,
-                Downloader::new(client.clone()),
-                Versions::new(client),
-                download,
-                nightly,
-            )
-            .execute(),
-        ),
+            min_interval,
+            force,
+            ..
+        } => {
+            let (config, transport) = load(&paths)?;
+            let output = match config.output(&paths) {
+                Ok(output) => output,
+                // Without a home directory, a check that installs nothing looks at PATH
+                Err(err) if !download => match which() {
+                    Some(path) => path.to_string_lossy().into(),
+                    None => return Err(err.into()),
+                },
+                Err(err) => return Err(err.into()),
+            };
+            let cache = crate::state::default_path(&paths).map(|path| Cache {
+                path,
+                api_url: config.api_url.clone(),
+                min_interval,
+                force,
+            });
+
+            Box::pin(run(
+                CheckCommand::new(
+                    output,
+                    downloader(transport.clone(), &config, &paths, cancel).build()?,
+                    versions(transport, &config, cancel).build()?,
+                    download,
+                    config.channel == Channel::Nightly,
+                )
+                .with_cache(cache)
+                .with_interactive(format == OutputFormat::Human),
+                format,
+            ))
+        }
+        Commands::Which { all } => Box::pin(run(WhichCommand::new(all), format)),
+        Commands::Doctor { .. } => {
+            let (config, transport) = load(&paths)?;
+
+            Box::pin(run(
+                DoctorCommand::new(
+                    config.output(&paths)?,
+                    paths.clone(),
+                    versions(transport, &config, cancel).build()?,
+                ),
+                format,
+            ))
+        }
+        Commands::SelfUpdate {
+            force,
+            release_api_url,
+            release_download_url,
+        } => {
+            let (config, transport) = load(&paths)?;
+
+            Box::pin(run(
+                SelfUpdateCommand::new(
+                    std::env::current_exe()?,
+                    downloader(transport.clone(), &config, &paths, cancel)
+                        .base_url(release_download_url)
+                        .asset(self_update::host_asset())
+                        .build()?,
+                    versions(transport, &config, cancel)
+                        .api_url(release_api_url)
+                        .build()?,
+                    force,
+                ),
+                format,
+            ))
+        }
+        Commands::Schedule { action } => {
+            // The scheduled check resolves its configuration the way this run would
+            let options = JobOptions::new()
+                .option("--profile", args.profile.clone())
+                .path("--config", args.config.clone())
+                .path("--root", args.root.clone())
+                .option("--download-url", args.download_url.clone())
+                .option("--api-url", args.api_url.clone())
+                .option("--retries", args.retries.map(|retries| retries.to_string()))
+                .option("--timeout", args.timeout.map(|timeout| timeout.to_string()))
+                .option("--target", args.target.clone());
+
+            Box::pin(run(
+                ScheduleCommand::new(action, std::env::current_exe()?, paths, options),
+                format,
+            ))
+        }
     };
 
-    match future.await {
-        Err(err) => Err(Box::new(err)),
-        Ok(_) => Ok(()),
-    }
+    Ok(future)
 }
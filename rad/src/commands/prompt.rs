@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tracing::debug;
+
+use super::command::{Command, Errors, Report};
+use crate::state::LastCheck;
+use rust_analyzer_downloader::rust_analyzer::version::Version;
+
+pub(super) const DEFAULT_MARKER: &str = "RA↑";
+
+#[derive(Debug, Serialize)]
+pub(super) struct PromptReport {
+    installed: Option<Version>,
+    update_available: bool,
+    /// Seconds since the Unix epoch of the check the answer comes from
+    checked_at: Option<u64>,
+    #[serde(skip)]
+    marker: String,
+}
+
+impl Report for PromptReport {
+    fn print(&self) {
+        if self.update_available {
+            println!("{}", self.marker);
+        }
+    }
+}
+
+/// Answers from the state recorded by `rad check`, it never runs rust-analyzer
+/// or touches the network so shell prompts can call it on every render.
+#[derive(Debug)]
+pub(super) struct PromptCommand {
+    /// Binary to answer for, the one the last check looked at when not set
+    output: Option<String>,
+    state: Option<PathBuf>,
+    marker: String,
+}
+
+impl PromptCommand {
+    pub(super) fn new(output: Option<String>, state: Option<PathBuf>, marker: String) -> Self {
+        Self {
+            output,
+            state,
+            marker,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Command for PromptCommand {
+    type Output = PromptReport;
+
+    async fn execute(self) -> Result<PromptReport, Errors> {
+        let last = self.state.as_deref().and_then(LastCheck::load);
+        let installed = last.as_ref().and_then(|last| {
+            let output = match &self.output {
+                Some(output) => output.as_str(),
+                None => last.installed.as_ref()?.path.as_str(),
+            };
+
+            last.installed(output)
+        });
+        debug!(output = ?self.output, installed = ?installed, "Last check");
+
+        Ok(PromptReport {
+            installed: installed.map(|installed| installed.version.clone()),
+            update_available: installed.map_or(false, |installed| installed.update_available),
+            checked_at: last.as_ref().map(|last| last.checked_at),
+            marker: self.marker,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{modified, Installed};
+
+    #[tokio::test]
+    async fn test_prompt_ignores_replaced_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("rust-analyzer");
+        let state = dir.path().join("last-check.json");
+        std::fs::write(&binary, "binary").unwrap();
+
+        let output = binary.to_string_lossy().to_string();
+        let mut last = LastCheck::new("https://example.com/releases".into(), Vec::new());
+        last.installed = Some(Installed {
+            path: output.clone(),
+            modified: modified(&binary).unwrap(),
+            version: Version {
+                date_version: "2022-08-15".into(),
+                semantic_version: "0.3.1000-standalone".into(),
+            },
+            update_available: true,
+        });
+        last.save(&state).unwrap();
+
+        let prompt = |output: Option<&str>| {
+            PromptCommand::new(
+                output.map(str::to_string),
+                Some(state.clone()),
+                DEFAULT_MARKER.into(),
+            )
+            .execute()
+        };
+
+        assert!(prompt(Some(&output)).await.unwrap().update_available);
+        assert!(prompt(None).await.unwrap().update_available);
+        assert!(!prompt(Some("/nonexistent")).await.unwrap().update_available);
+
+        last.installed.as_mut().unwrap().modified -= 60;
+        last.save(&state).unwrap();
+        assert!(!prompt(Some(&output)).await.unwrap().update_available);
+        assert!(!prompt(None).await.unwrap().update_available);
+    }
+}
@@ -0,0 +1,802 @@
+use std::ffi::{OsStr, OsString};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use clap::{Subcommand, ValueEnum};
+use directories::BaseDirs;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command as Process;
+use tracing::{debug, info};
+
+use rust_analyzer_downloader::paths::{Error as PathsError, Paths};
+
+use super::command::{Command, Errors, Report};
+use crate::config::Error as ConfigError;
+
+const UNIT: &str = "rad-check";
+/// Trailing comment that marks the crontab line managed by rad
+const CRON_MARKER: &str = "# rad-check";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Every {
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+impl Every {
+    /// systemd `OnCalendar=` expression, daily and weekly runs happen in the morning
+    fn on_calendar(&self) -> &'static str {
+        match self {
+            Every::Hourly => "hourly",
+            Every::Daily => "*-*-* 09:00:00",
+            Every::Weekly => "Mon *-*-* 09:00:00",
+        }
+    }
+
+    fn cron(&self) -> &'static str {
+        match self {
+            Every::Hourly => "0 * * * *",
+            Every::Daily => "0 9 * * *",
+            Every::Weekly => "0 9 * * 1",
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub(super) enum ScheduleAction {
+    /// Runs `rad check --download` periodically through a systemd user timer or cron
+    Install {
+        #[clap(long, value_enum, default_value_t = Every::Daily)]
+        every: Every,
+
+        /// Binary the scheduled check installs
+        #[clap(short, long, required = false, value_parser)]
+        output: Option<PathBuf>,
+
+        /// Installs nightly releases
+        #[clap(short, long, required = false, value_parser, default_value_t = false)]
+        nightly: bool,
+    },
+    /// Shows whether the scheduled check is installed and when it runs next
+    Status,
+    /// Removes the scheduled check
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    Systemd,
+    Cron,
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Backend::Systemd => write!(f, "systemd user timer"),
+            Backend::Cron => write!(f, "crontab"),
+        }
+    }
+}
+
+/// Quotes a value for the shell that cron runs the line with, a `%` ends the
+/// command in a crontab unless it is escaped.
+fn shell_quote(value: &OsStr) -> String {
+    format!(
+        "'{}'",
+        value
+            .to_string_lossy()
+            .replace('\'', r"'\''")
+            .replace('%', r"\%")
+    )
+}
+
+/// Quotes a value for `ExecStart=`, systemd splits words itself and expands
+/// `%` specifiers and `$` variables.
+fn systemd_quote(value: &OsStr) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .to_string_lossy()
+            .replace('\\', r"\\")
+            .replace('"', r#"\""#)
+            .replace('%', "%%")
+            .replace('$', "$$")
+    )
+}
+
+/// Options of the `rad` invocation that installed the schedule, the scheduled
+/// check runs with the same ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct JobOptions {
+    options: Vec<(&'static str, Option<OsString>)>,
+}
+
+impl JobOptions {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `name value` when `value` is set.
+    pub(super) fn option(mut self, name: &'static str, value: Option<impl Into<OsString>>) -> Self {
+        if let Some(value) = value {
+            self.options.push((name, Some(value.into())));
+        }
+        self
+    }
+
+    /// Adds `name path` when `path` is set, relative to the current directory
+    /// since the job runs from elsewhere.
+    pub(super) fn path(self, name: &'static str, path: Option<PathBuf>) -> Self {
+        let path = path.map(|path| match std::env::current_dir() {
+            Ok(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        });
+
+        self.option(name, path)
+    }
+
+    /// Adds `name` when `set`.
+    pub(super) fn flag(mut self, name: &'static str, set: bool) -> Self {
+        if set {
+            self.options.push((name, None));
+        }
+        self
+    }
+
+    /// `<exe> check --download <options> --log-file <log_file>` with every value quoted.
+    fn command_line(&self, exe: &Path, log_file: &Path, quote: fn(&OsStr) -> String) -> String {
+        let mut line = format!("{} check --download", quote(exe.as_os_str()));
+
+        for (name, value) in &self.options {
+            line.push(' ');
+            line.push_str(name);
+            if let Some(value) = value {
+                line.push(' ');
+                line.push_str(&quote(value));
+            }
+        }
+
+        line.push_str(" --log-file ");
+        line.push_str(&quote(log_file.as_os_str()));
+        line
+    }
+}
+
+fn service_unit(command_line: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Update rust-analyzer with rad\n\
+         Wants=network-online.target\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart={}\n",
+        command_line
+    )
+}
+
+fn timer_unit(every: Every) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Update rust-analyzer with rad ({:?})\n\
+         \n\
+         [Timer]\n\
+         OnCalendar={}\n\
+         Persistent=true\n\
+         RandomizedDelaySec=300\n\
+         \n\
+         [Install]\n\
+         WantedBy=timers.target\n",
+        every,
+        every.on_calendar()
+    )
+}
+
+/// Crontab without the line managed by rad, plus `line` when given.
+fn crontab(current: &str, line: Option<&str>) -> String {
+    let mut lines: Vec<&str> = current
+        .lines()
+        .filter(|line| !line.ends_with(CRON_MARKER))
+        .collect();
+    lines.extend(line);
+
+    let mut crontab = lines.join("\n");
+    if !crontab.is_empty() {
+        crontab.push('\n');
+    }
+
+    crontab
+}
+
+fn cron_line(every: Every, command_line: &str) -> String {
+    format!("{} {} {}", every.cron(), command_line, CRON_MARKER)
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ScheduleReport {
+    backend: Backend,
+    installed: bool,
+    schedule: Option<String>,
+    next_run: Option<String>,
+    log_file: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl Report for ScheduleReport {
+    fn print(&self) {
+        if !self.installed {
+            println!("No scheduled check is installed ({})", self.backend);
+            return;
+        }
+
+        println!("Scheduled check installed ({})", self.backend);
+        if let Some(schedule) = &self.schedule {
+            println!("Schedule: {}", schedule);
+        }
+        if let Some(next_run) = &self.next_run {
+            println!("Next run: {}", next_run);
+        }
+        println!("Log file: {}", self.log_file.display());
+        for file in &self.files {
+            println!("File:     {}", file.display());
+        }
+    }
+}
+
+/// Where the unit files and the log of the scheduled check are kept.
+#[derive(Debug, Clone)]
+struct Locations {
+    unit_dir: PathBuf,
+    log_file: PathBuf,
+}
+
+impl Locations {
+    /// The log is kept in the state directory of `paths` like the state of
+    /// `check`, the units go where systemd looks for them whatever the root.
+    fn new(paths: &Paths) -> Result<Self, PathsError> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => BaseDirs::new()
+                .map(|dirs| dirs.config_dir().to_path_buf())
+                .ok_or(PathsError::Unresolved("config", "XDG_CONFIG_HOME"))?,
+        };
+
+        Ok(Self {
+            unit_dir: config_dir.join("systemd").join("user"),
+            log_file: paths.state_dir()?.join("check.log"),
+        })
+    }
+
+    fn unit_files(&self) -> [PathBuf; 2] {
+        [
+            self.unit_dir.join(format!("{}.service", UNIT)),
+            self.unit_dir.join(format!("{}.timer", UNIT)),
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct ScheduleCommand {
+    action: ScheduleAction,
+    exe: PathBuf,
+    paths: Paths,
+    options: JobOptions,
+    /// Resolved from `paths` and the home directory when not set
+    locations: Option<Locations>,
+    /// `PATH` to find systemctl and crontab in, the inherited one when not set
+    search_path: Option<OsString>,
+}
+
+impl ScheduleCommand {
+    pub(super) fn new(
+        action: ScheduleAction,
+        exe: PathBuf,
+        paths: Paths,
+        options: JobOptions,
+    ) -> Self {
+        Self {
+            action,
+            exe,
+            paths,
+            options,
+            locations: None,
+            search_path: None,
+        }
+    }
+
+    fn locations(&self) -> Result<Locations, Errors> {
+        match &self.locations {
+            Some(locations) => Ok(locations.clone()),
+            None => Ok(Locations::new(&self.paths).map_err(ConfigError::from)?),
+        }
+    }
+
+    fn process(&self, program: &str) -> Process {
+        let mut process = Process::new(program);
+        if let Some(path) = &self.search_path {
+            process.env("PATH", path);
+        }
+
+        process
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> Result<String, Errors> {
+        debug!(program = program, args = ?args, "Running");
+        let output = self.process(program).args(args).output().await?;
+
+        if !output.status.success() {
+            return Err(Errors::Schedule(format!(
+                "{} {} failed: {}",
+                program,
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    async fn detect(&self) -> Result<Backend, Errors> {
+        if self
+            .run("systemctl", &["--user", "show-environment"])
+            .await
+            .is_ok()
+        {
+            return Ok(Backend::Systemd);
+        }
+
+        if self
+            .process("crontab")
+            .arg("-l")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await
+            .is_ok()
+        {
+            return Ok(Backend::Cron);
+        }
+
+        Err(Errors::Schedule(
+            "neither systemd user services nor crontab are available".into(),
+        ))
+    }
+
+    async fn read_crontab(&self) -> String {
+        // `crontab -l` fails when the user has no crontab yet
+        self.run("crontab", &["-l"]).await.unwrap_or_default()
+    }
+
+    async fn write_crontab(&self, content: &str) -> Result<(), Errors> {
+        let mut child = self
+            .process("crontab")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(content.as_bytes()).await?;
+        }
+
+        if !child.wait().await?.success() {
+            return Err(Errors::Schedule("crontab - failed".into()));
+        }
+
+        Ok(())
+    }
+
+    async fn install(
+        &self,
+        backend: Backend,
+        every: Every,
+        output: Option<PathBuf>,
+        nightly: bool,
+    ) -> Result<(), Errors> {
+        let locations = self.locations()?;
+        let options = self
+            .options
+            .clone()
+            .path("--output", output)
+            .flag("--nightly", nightly);
+
+        match backend {
+            Backend::Systemd => {
+                let [service, timer] = locations.unit_files();
+                tokio::fs::create_dir_all(&locations.unit_dir).await?;
+                let command_line =
+                    options.command_line(&self.exe, &locations.log_file, systemd_quote);
+                tokio::fs::write(&service, service_unit(&command_line)).await?;
+                tokio::fs::write(&timer, timer_unit(every)).await?;
+
+                let timer_name = format!("{}.timer", UNIT);
+                self.run("systemctl", &["--user", "daemon-reload"]).await?;
+                self.run("systemctl", &["--user", "enable", "--now", &timer_name])
+                    .await?;
+            }
+            Backend::Cron => {
+                let command_line =
+                    options.command_line(&self.exe, &locations.log_file, shell_quote);
+                let line = cron_line(every, &command_line);
+                self.write_crontab(&crontab(&self.read_crontab().await, Some(&line)))
+                    .await?;
+            }
+        }
+
+        info!(backend = %backend, every = ?every, "Scheduled check installed");
+        Ok(())
+    }
+
+    async fn remove(&self, backend: Backend) -> Result<(), Errors> {
+        match backend {
+            Backend::Systemd => {
+                let timer_name = format!("{}.timer", UNIT);
+                // Fails when the timer was never enabled, the unit files are removed anyway
+                let _ = self
+                    .run("systemctl", &["--user", "disable", "--now", &timer_name])
+                    .await;
+
+                for file in self.locations()?.unit_files() {
+                    match tokio::fs::remove_file(&file).await {
+                        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                            return Err(err.into())
+                        }
+                        _ => {}
+                    }
+                }
+
+                self.run("systemctl", &["--user", "daemon-reload"]).await?;
+            }
+            Backend::Cron => {
+                self.write_crontab(&crontab(&self.read_crontab().await, None))
+                    .await?
+            }
+        }
+
+        info!(backend = %backend, "Scheduled check removed");
+        Ok(())
+    }
+
+    async fn status(&self, backend: Backend) -> Result<ScheduleReport, Errors> {
+        let locations = self.locations()?;
+        let mut report = ScheduleReport {
+            backend,
+            installed: false,
+            schedule: None,
+            next_run: None,
+            log_file: locations.log_file.clone(),
+            files: Vec::new(),
+        };
+
+        match backend {
+            Backend::Systemd => {
+                let [service, timer] = locations.unit_files();
+                if !timer.exists() {
+                    return Ok(report);
+                }
+
+                let timer_name = format!("{}.timer", UNIT);
+                let show = |property: &'static str| {
+                    let timer_name = timer_name.clone();
+                    async move {
+                        let args = ["--user", "show", &timer_name, "-p", property, "--value"];
+                        self.run("systemctl", &args)
+                            .await
+                            .ok()
+                            .map(|value| value.trim().to_string())
+                            .filter(|value| !value.is_empty())
+                    }
+                };
+
+                report.installed = show("UnitFileState").await.as_deref() == Some("enabled");
+                report.schedule = std::fs::read_to_string(&timer)?
+                    .lines()
+                    .find_map(|line| line.strip_prefix("OnCalendar="))
+                    .map(str::to_string);
+                report.next_run = show("NextElapseUSecRealtime").await;
+                report.files = vec![service, timer];
+            }
+            Backend::Cron => {
+                let crontab = self.read_crontab().await;
+                let line = crontab.lines().find(|line| line.ends_with(CRON_MARKER));
+
+                report.installed = line.is_some();
+                report.schedule = line.map(|line| {
+                    line.split_whitespace()
+                        .take(5)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                });
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[async_trait::async_trait]
+impl Command for ScheduleCommand {
+    type Output = ScheduleReport;
+
+    async fn execute(self) -> Result<ScheduleReport, Errors> {
+        let backend = self.detect().await?;
+        debug!(backend = %backend, "Scheduler detected");
+
+        match &self.action {
+            ScheduleAction::Install {
+                every,
+                output,
+                nightly,
+            } => {
+                self.install(backend, *every, output.clone(), *nightly)
+                    .await?
+            }
+            ScheduleAction::Remove => self.remove(backend).await?,
+            ScheduleAction::Status => {}
+        }
+
+        self.status(backend).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units_run_check_with_log_file() {
+        let exe = Path::new("/home/user/bin/rad");
+        let log = Path::new("/home/user/.local/state/rad/check.log");
+        let service = service_unit(&JobOptions::new().command_line(exe, log, systemd_quote));
+        let timer = timer_unit(Every::Daily);
+
+        assert!(service.contains(
+            "ExecStart=\"/home/user/bin/rad\" check --download --log-file \"/home/user/.local/state/rad/check.log\""
+        ));
+        assert!(timer.contains("OnCalendar=*-*-* 09:00:00\n"));
+        assert!(timer.contains("Persistent=true\n"));
+    }
+
+    #[test]
+    fn test_units_keep_options() {
+        let options = JobOptions::new()
+            .option("--profile", Some("work"))
+            .path("--root", Some(PathBuf::from("/srv/rad")))
+            .option("--retries", None::<String>)
+            .flag("--nightly", true)
+            .flag("--quiet", false);
+        let service = service_unit(&options.command_line(
+            Path::new("/home/user/bin/rad"),
+            Path::new("/srv/rad/state/check.log"),
+            systemd_quote,
+        ));
+
+        assert!(service.contains(
+            "ExecStart=\"/home/user/bin/rad\" check --download --profile \"work\" --root \"/srv/rad\" --nightly --log-file \"/srv/rad/state/check.log\"\n"
+        ));
+    }
+
+    #[test]
+    fn test_crontab_replaces_managed_line() {
+        let exe = Path::new("/usr/bin/rad");
+        let log = Path::new("/tmp/check.log");
+        let line = |every, options: &JobOptions| {
+            cron_line(every, &options.command_line(exe, log, shell_quote))
+        };
+        let existing = format!(
+            "MAILTO=\"\"\n{}\n0 0 * * * backup\n",
+            line(Every::Hourly, &JobOptions::new())
+        );
+
+        let updated = crontab(&existing, Some(&line(Every::Daily, &JobOptions::new())));
+
+        assert_eq!(
+            updated,
+            "MAILTO=\"\"\n0 0 * * * backup\n0 9 * * * '/usr/bin/rad' check --download --log-file '/tmp/check.log' # rad-check\n"
+        );
+        assert_eq!(crontab(&updated, None), "MAILTO=\"\"\n0 0 * * * backup\n");
+
+        let options = JobOptions::new()
+            .path("--config", Some(PathBuf::from("/etc/rad/100%.toml")))
+            .option("--output", Some("/opt/rust-analyzer"));
+        assert_eq!(
+            line(Every::Daily, &options),
+            "0 9 * * * '/usr/bin/rad' check --download --config '/etc/rad/100\\%.toml' --output '/opt/rust-analyzer' --log-file '/tmp/check.log' # rad-check"
+        );
+    }
+
+    #[test]
+    fn test_quoting() {
+        let path = OsStr::new("/home/o'neil/100% \"$HOME\"/rad");
+
+        assert_eq!(
+            systemd_quote(path),
+            r#""/home/o'neil/100%% \"$$HOME\"/rad""#
+        );
+        assert_eq!(shell_quote(path), r#"'/home/o'\''neil/100\% "$HOME"/rad'"#);
+    }
+
+    #[cfg(target_family = "unix")]
+    mod fake {
+        use std::os::unix::fs::PermissionsExt;
+
+        use super::*;
+
+        /// Logs its arguments, reports the timer as enabled.
+        const SYSTEMCTL: &str = r#"#!/bin/sh
+echo "$*" >> "$(dirname "$0")/systemctl.log"
+case "$*" in
+    *UnitFileState*) echo enabled ;;
+esac
+"#;
+
+        /// Keeps the crontab next to the script, `-l` fails until one is written.
+        const CRONTAB: &str = r#"#!/bin/sh
+file="$(dirname "$0")/crontab.txt"
+case "$1" in
+    -l)
+        [ -f "$file" ] || exit 1
+        while IFS= read -r line; do printf '%s\n' "$line"; done < "$file"
+        ;;
+    -) while IFS= read -r line; do printf '%s\n' "$line"; done > "$file" ;;
+esac
+"#;
+
+        struct Fake {
+            root: tempfile::TempDir,
+        }
+
+        impl Fake {
+            fn new(tools: &[(&str, &str)]) -> Self {
+                let root = tempfile::tempdir().unwrap();
+                let bin = root.path().join("bin");
+                std::fs::create_dir_all(&bin).unwrap();
+
+                for (name, script) in tools {
+                    let path = bin.join(name);
+                    std::fs::write(&path, script).unwrap();
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                        .unwrap();
+                }
+
+                Self { root }
+            }
+
+            fn bin(&self, name: &str) -> PathBuf {
+                self.root.path().join("bin").join(name)
+            }
+
+            fn command(&self, action: ScheduleAction) -> ScheduleCommand {
+                ScheduleCommand {
+                    action,
+                    exe: PathBuf::from("/opt/rad/bin/rad"),
+                    paths: Paths::new().with_root(self.root.path()),
+                    options: JobOptions::new().option("--profile", Some("work")),
+                    locations: Some(Locations {
+                        unit_dir: self.root.path().join("systemd").join("user"),
+                        log_file: self.root.path().join("state").join("check.log"),
+                    }),
+                    // dirname is the only tool the scripts need besides themselves
+                    search_path: Some(
+                        std::env::join_paths([self.root.path().join("bin"), which_dirname()])
+                            .unwrap(),
+                    ),
+                }
+            }
+        }
+
+        fn which_dirname() -> PathBuf {
+            ["/usr/bin", "/bin"]
+                .iter()
+                .map(PathBuf::from)
+                .find(|dir| dir.join("dirname").is_file())
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn test_detect() {
+            let both = Fake::new(&[("systemctl", SYSTEMCTL), ("crontab", CRONTAB)]);
+            let cron = Fake::new(&[("crontab", CRONTAB)]);
+            let none = Fake::new(&[]);
+
+            assert_eq!(
+                both.command(ScheduleAction::Status).detect().await.unwrap(),
+                Backend::Systemd
+            );
+            assert_eq!(
+                cron.command(ScheduleAction::Status).detect().await.unwrap(),
+                Backend::Cron
+            );
+            assert!(matches!(
+                none.command(ScheduleAction::Status).detect().await,
+                Err(Errors::Schedule(_))
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_systemd_install_and_remove() {
+            let fake = Fake::new(&[("systemctl", SYSTEMCTL)]);
+            let install = || ScheduleAction::Install {
+                every: Every::Weekly,
+                output: None,
+                nightly: true,
+            };
+
+            fake.command(install()).execute().await.unwrap();
+            let report = fake.command(install()).execute().await.unwrap();
+
+            assert!(report.installed);
+            assert_eq!(report.schedule.as_deref(), Some("Mon *-*-* 09:00:00"));
+            let [service, timer] = fake.command(install()).locations().unwrap().unit_files();
+            assert!(std::fs::read_to_string(&service)
+                .unwrap()
+                .contains("ExecStart=\"/opt/rad/bin/rad\" check --download --profile \"work\" --nightly --log-file"));
+            assert!(timer.exists());
+
+            let report = fake
+                .command(ScheduleAction::Remove)
+                .execute()
+                .await
+                .unwrap();
+
+            assert!(!report.installed);
+            assert!(!service.exists() && !timer.exists());
+            let log = std::fs::read_to_string(fake.bin("systemctl.log")).unwrap();
+            assert_eq!(
+                log.lines()
+                    .filter(|line| line.contains("enable --now rad-check.timer"))
+                    .count(),
+                2
+            );
+            assert!(log.contains("--user disable --now rad-check.timer"));
+        }
+
+        #[tokio::test]
+        async fn test_cron_install_and_remove() {
+            let fake = Fake::new(&[("crontab", CRONTAB)]);
+            std::fs::write(fake.bin("crontab.txt"), "0 0 * * * backup\n").unwrap();
+
+            fake.command(ScheduleAction::Install {
+                every: Every::Daily,
+                output: None,
+                nightly: false,
+            })
+            .execute()
+            .await
+            .unwrap();
+            let report = fake
+                .command(ScheduleAction::Install {
+                    every: Every::Hourly,
+                    output: Some(PathBuf::from("/opt/rust-analyzer")),
+                    nightly: false,
+                })
+                .execute()
+                .await
+                .unwrap();
+
+            assert!(report.installed);
+            assert_eq!(report.schedule.as_deref(), Some("0 * * * *"));
+            let content = std::fs::read_to_string(fake.bin("crontab.txt")).unwrap();
+            assert_eq!(content.matches(CRON_MARKER).count(), 1);
+            assert!(content.contains("--profile 'work' --output '/opt/rust-analyzer' --log-file"));
+            assert!(content.starts_with("0 0 * * * backup\n"));
+
+            let report = fake
+                .command(ScheduleAction::Remove)
+                .execute()
+                .await
+                .unwrap();
+
+            assert!(!report.installed);
+            assert_eq!(
+                std::fs::read_to_string(fake.bin("crontab.txt")).unwrap(),
+                "0 0 * * * backup\n"
+            );
+        }
+    }
+}
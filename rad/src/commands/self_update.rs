@@ -0,0 +1,310 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tokio::process::Command as Process;
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+
+use super::command::{Command, Errors, Report};
+use rust_analyzer_downloader::rust_analyzer::version::DEFAULT_TIMEOUT;
+use rust_analyzer_downloader::services::downloader::{Downloader, InstallReport};
+use rust_analyzer_downloader::services::versions::{Paging, Versions};
+
+pub(super) const SELF_RELEASE_API_URL: &str =
+    "https://api.github.com/repos/malusev998/rust-analyzer-downloader/releases";
+pub(super) const SELF_DOWNLOAD_URL: &str =
+    "https://github.com/malusev998/rust-analyzer-downloader/releases/download";
+
+/// Name of the release asset built for this host by the release workflow.
+pub(super) fn host_asset() -> &'static str {
+    #[cfg(target_os = "windows")]
+    return "rad-windows-latest.exe";
+
+    #[cfg(target_os = "macos")]
+    return "rad-macos-latest";
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    return "rad-ubuntu-latest";
+}
+
+/// Numeric `major.minor.patch` of a tag or version, followed by whether it is
+/// a final release, so `2.4.0` sorts after `2.4.0-dev`.
+fn parse_version(version: &str) -> Option<(u64, u64, u64, bool)> {
+    let version = version.trim().trim_start_matches('v');
+    let version = version.split('+').next()?;
+    let (core, released) = match version.split_once('-') {
+        Some((core, _)) => (core, false),
+        None => (version, true),
+    };
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+
+    Some((
+        parts.next()??,
+        parts.next()??,
+        parts.next().flatten().unwrap_or(0),
+        released,
+    ))
+}
+
+fn is_newer(current: &str, latest: &str) -> bool {
+    match (parse_version(current), parse_version(latest)) {
+        (Some(current), Some(latest)) => latest > current,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct SelfUpdateReport {
+    current: String,
+    latest: Option<String>,
+    updated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    install: Option<InstallReport>,
+}
+
+impl Report for SelfUpdateReport {
+    fn print(&self) {
+        match (&self.latest, &self.install) {
+            (Some(latest), Some(install)) => {
+                println!("Updated rad {} -> {}", self.current, latest);
+                super::download::print_install(install);
+            }
+            (Some(latest), None) => println!("rad {} is up to date ({})", self.current, latest),
+            (None, _) => println!("No rad releases found"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct SelfUpdateCommand {
+    exe: PathBuf,
+    downloader: Downloader,
+    versions: Versions,
+    force: bool,
+}
+
+impl SelfUpdateCommand {
+    pub(super) fn new(
+        exe: PathBuf,
+        downloader: Downloader,
+        versions: Versions,
+        force: bool,
+    ) -> Self {
+        Self {
+            exe,
+            downloader,
+            versions,
+            force,
+        }
+    }
+
+    async fn latest(&self) -> Result<Option<String>, Errors> {
+        let releases = match self.versions.get(1, 5).await? {
+            Paging::Next(_, releases) => releases,
+            Paging::Done => return Ok(None),
+        };
+
+        Ok(releases
+            .into_iter()
+            .find(|release| !release.prerelease)
+            .map(|release| release.tag_name))
+    }
+
+    /// Runs the staged binary and checks that it reports the expected version.
+    async fn verify(&self, staged: &Path, tag: &str) -> Result<(), Errors> {
+        let command = Process::new(staged)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output();
+
+        let output = match timeout(DEFAULT_TIMEOUT, command).await {
+            Ok(output) => output?,
+            Err(_) => {
+                return Err(Errors::SelfUpdate(format!(
+                    "{} --version timed out",
+                    staged.display()
+                )))
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let reported = stdout.split_whitespace().last().unwrap_or_default();
+        debug!(reported = reported, tag = tag, "Staged binary version");
+
+        if !output.status.success() || parse_version(reported) != parse_version(tag) {
+            return Err(Errors::SelfUpdate(format!(
+                "downloaded binary reported {:?}, expected version {}",
+                stdout.trim(),
+                tag
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the staged binary in, the running executable is moved aside first
+    /// on Windows because it cannot be overwritten while it runs.
+    fn replace(&self, staged: &Path) -> Result<(), Errors> {
+        #[cfg(target_family = "windows")]
+        {
+            let old = self.exe.with_extension("old");
+            let _ = std::fs::remove_file(&old);
+            std::fs::rename(&self.exe, &old)?;
+
+            if let Err(err) = std::fs::rename(staged, &self.exe) {
+                std::fs::rename(&old, &self.exe)?;
+                return Err(err.into());
+            }
+        }
+
+        // Same directory, so the rename is atomic
+        #[cfg(not(target_family = "windows"))]
+        std::fs::rename(staged, &self.exe)?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Command for SelfUpdateCommand {
+    type Output = SelfUpdateReport;
+
+    async fn execute(self) -> Result<SelfUpdateReport, Errors> {
+        let current = env!("CARGO_PKG_VERSION").to_string();
+        let latest = self.latest().await?;
+
+        let tag = match &latest {
+            Some(tag) if self.force || is_newer(&current, tag) => tag.clone(),
+            _ => {
+                info!(current = current.as_str(), latest = ?latest, "rad is up to date");
+                return Ok(SelfUpdateReport {
+                    current,
+                    latest,
+                    updated: false,
+                    install: None,
+                });
+            }
+        };
+
+        let file_name = self
+            .exe
+            .file_name()
+            .ok_or_else(|| Errors::SelfUpdate(format!("{} is not a file", self.exe.display())))?;
+        let staged = self
+            .exe
+            .with_file_name(format!(".{}.new", file_name.to_string_lossy()));
+
+        info!(
+            current = current.as_str(),
+            latest = tag.as_str(),
+            "Updating rad"
+        );
+        let install = self
+            .downloader
+            .download(&tag, &staged.to_string_lossy())
+            .await?;
+
+        let result = match self.verify(&staged, &tag).await {
+            Ok(_) => self.replace(&staged),
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            warn!(error = %err, "Keeping the current executable");
+            let _ = std::fs::remove_file(&staged);
+            return Err(err);
+        }
+
+        Ok(SelfUpdateReport {
+            current,
+            latest,
+            updated: true,
+            install: Some(InstallReport {
+                path: self.exe,
+                ..install
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("2.4.0-dev", "v2.5.0"));
+        assert!(is_newer("2.4.0", "2.4.1"));
+        assert!(is_newer("2.4.0-dev", "v2.4.0"));
+        assert!(!is_newer("2.4.0", "v2.4.0"));
+        assert!(!is_newer("2.4.0", "v2.3.9"));
+        assert!(!is_newer("2.4.0", "nightly"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_self_update_from_fake_release_server() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                while !reader.fill_buf().unwrap().starts_with(b"\r\n") {
+                    reader.read_line(&mut String::new()).unwrap();
+                }
+
+                let body = if request_line.starts_with("GET /releases") {
+                    r#"[{"name": "Release v99.0.0", "tag_name": "v99.0.0", "prerelease": false}]"#
+                } else {
+                    assert!(request_line.starts_with("GET /download/v99.0.0/rad-"));
+                    "#!/bin/sh\necho rust-analyzer-downloader 99.0.0\n"
+                };
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("rad");
+        std::fs::write(&exe, "old").unwrap();
+
+        let client = reqwest::Client::new();
+        let command = SelfUpdateCommand::new(
+            exe.clone(),
+            Downloader::builder()
+                .client(client.clone())
+                .base_url(format!("{}/download", url))
+                .asset(host_asset())
+                .temp_dir(dir.path())
+                .build()
+                .unwrap(),
+            Versions::builder()
+                .client(client)
+                .api_url(format!("{}/releases", url))
+                .build()
+                .unwrap(),
+            false,
+        );
+
+        let report = command.execute().await.unwrap();
+        server.join().unwrap();
+
+        assert!(report.updated);
+        assert_eq!(report.latest.as_deref(), Some("v99.0.0"));
+        assert!(std::fs::read_to_string(&exe).unwrap().contains("99.0.0"));
+        assert!(!dir.path().join(".rad.new").exists());
+    }
+}
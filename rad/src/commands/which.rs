@@ -0,0 +1,97 @@
+use super::command::{Command, Errors, Report};
+use rust_analyzer_downloader::rust_analyzer::discovery::{discover, Installation};
+use rust_analyzer_downloader::rust_analyzer::version::{Version, DEFAULT_TIMEOUT};
+use serde::Serialize;
+use tracing::{info, warn};
+
+#[derive(Debug)]
+pub(super) struct WhichCommand {
+    all: bool,
+}
+
+impl WhichCommand {
+    pub(super) fn new(all: bool) -> Self {
+        Self { all }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct Binary {
+    path: String,
+    source: String,
+    version: Option<Version>,
+    error: Option<String>,
+    on_path: bool,
+    active: bool,
+}
+
+impl From<Installation> for Binary {
+    fn from(installation: Installation) -> Self {
+        let (version, error) = match installation.version {
+            Ok(version) => (Some(version), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        Self {
+            path: installation.path.display().to_string(),
+            source: installation.source.to_string(),
+            version,
+            error,
+            on_path: installation.on_path,
+            active: installation.active,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct Binaries {
+    binaries: Vec<Binary>,
+}
+
+impl Report for Binaries {}
+
+fn report(binary: &Binary) {
+    match (&binary.version, &binary.error) {
+        (Some(version), _) => info!(
+            path = binary.path.as_str(),
+            version = version.date_version.as_str(),
+            semantic_version = version.semantic_version.as_str(),
+            source = binary.source.as_str(),
+            on_path = binary.on_path,
+            active = binary.active,
+        ),
+        (None, error) => warn!(
+            path = binary.path.as_str(),
+            error = error.as_deref().unwrap_or_default(),
+            source = binary.source.as_str(),
+            on_path = binary.on_path,
+            active = binary.active,
+        ),
+    }
+}
+
+#[async_trait::async_trait]
+impl Command for WhichCommand {
+    type Output = Binaries;
+
+    async fn execute(self) -> Result<Binaries, Errors> {
+        let binaries = discover(DEFAULT_TIMEOUT)
+            .await
+            .into_iter()
+            .map(Binary::from)
+            .filter(|binary| self.all || binary.active)
+            .collect::<Vec<_>>();
+
+        binaries.iter().for_each(report);
+
+        if binaries.is_empty() {
+            if self.all {
+                warn!("No rust-analyzer installation found");
+            } else {
+                warn!("No rust-analyzer on PATH, use --all to list other installations");
+            }
+        }
+
+        Ok(Binaries { binaries })
+    }
+}
@@ -0,0 +1,429 @@
+//! Settings resolution for `rad`.
+//!
+//! Every setting is resolved independently, the first layer that provides it wins:
+//!
+//! 1. command line flags
+//! 2. environment variables
+//! 3. the selected profile in the config file
+//! 4. top level keys in the config file
+//! 5. built-in defaults
+//!
+//! The config file lives at `$XDG_CONFIG_HOME/rad/config.toml`, it can be moved with
+//! `--config` or `RAD_CONFIG`. A profile is selected with `--profile`, `RAD_PROFILE`
+//! or the `profile` key of the config file.
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Deserialize;
+use thiserror::Error as ThisError;
+use tracing::debug;
+
+use rust_analyzer_downloader::paths::{Error as PathsError, Paths};
+use rust_analyzer_downloader::services::downloader::{host_target, DEFAULT_DOWNLOAD_URL};
+use rust_analyzer_downloader::services::versions::RELEASE_GITHUB_API_URL;
+
+#[derive(Debug, ThisError)]
+pub(crate) enum Error {
+    #[error("Failed to read config file {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("Failed to parse config file {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+
+    #[error("Profile '{0}' is not defined in the config file")]
+    UnknownProfile(String),
+
+    #[error("Invalid value '{1}' for {0}")]
+    InvalidValue(&'static str, String),
+
+    #[error("Failed to read GitHub token: {0}")]
+    Token(String),
+
+    #[error(transparent)]
+    Paths(#[from] PathsError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Channel {
+    Stable,
+    Nightly,
+}
+
+impl FromStr for Channel {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "stable" => Ok(Channel::Stable),
+            "nightly" => Ok(Channel::Nightly),
+            _ => Err(Error::InvalidValue("channel", value.to_string())),
+        }
+    }
+}
+
+/// Where the GitHub token comes from, eg. `token = { command = "gh auth token" }`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TokenSource {
+    Value(String),
+    Env(String),
+    Command(String),
+    File(PathBuf),
+}
+
+impl TokenSource {
+    /// Reads the token, only done by commands that send requests.
+    pub(crate) fn resolve(&self) -> Result<String, Error> {
+        let token = match self {
+            TokenSource::Value(token) => token.clone(),
+            TokenSource::Env(name) => {
+                std::env::var(name).map_err(|err| Error::Token(format!("{}: {}", name, err)))?
+            }
+            TokenSource::File(path) => std::fs::read_to_string(path)
+                .map_err(|err| Error::Token(format!("{}: {}", path.display(), err)))?,
+            TokenSource::Command(command) => {
+                #[cfg(target_family = "windows")]
+                let output = std::process::Command::new("cmd")
+                    .args(["/C", command])
+                    .output();
+                #[cfg(target_family = "unix")]
+                let output = std::process::Command::new("sh")
+                    .args(["-c", command])
+                    .output();
+
+                match output {
+                    Ok(output) if output.status.success() => {
+                        String::from_utf8_lossy(&output.stdout).into_owned()
+                    }
+                    Ok(output) => {
+                        return Err(Error::Token(format!("'{}' {}", command, output.status)))
+                    }
+                    Err(err) => return Err(Error::Token(format!("'{}': {}", command, err))),
+                }
+            }
+        };
+
+        Ok(token.trim().to_string())
+    }
+}
+
+/// One layer of settings, unset fields fall through to the next layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub(crate) struct Settings {
+    pub(crate) output: Option<String>,
+    pub(crate) channel: Option<Channel>,
+    pub(crate) download_url: Option<String>,
+    pub(crate) api_url: Option<String>,
+    pub(crate) token: Option<TokenSource>,
+    pub(crate) retries: Option<u32>,
+    /// Request timeout in seconds
+    pub(crate) timeout: Option<u64>,
+    pub(crate) target: Option<String>,
+}
+
+impl Settings {
+    fn or(self, other: Settings) -> Settings {
+        Settings {
+            output: self.output.or(other.output),
+            channel: self.channel.or(other.channel),
+            download_url: self.download_url.or(other.download_url),
+            api_url: self.api_url.or(other.api_url),
+            token: self.token.or(other.token),
+            retries: self.retries.or(other.retries),
+            timeout: self.timeout.or(other.timeout),
+            target: self.target.or(other.target),
+        }
+    }
+
+    fn from_env<F>(lookup: F) -> Result<Settings, Error>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        fn parse<T: FromStr>(
+            name: &'static str,
+            value: Option<String>,
+        ) -> Result<Option<T>, Error> {
+            value
+                .map(|value| value.parse().map_err(|_| Error::InvalidValue(name, value)))
+                .transpose()
+        }
+
+        Ok(Settings {
+            output: lookup("RAD_OUTPUT_PATH"),
+            channel: lookup("RAD_CHANNEL")
+                .map(|value| value.parse())
+                .transpose()?,
+            download_url: lookup("RAD_DOWNLOAD_URL"),
+            api_url: lookup("RAD_API_URL"),
+            token: lookup("GITHUB_TOKEN").map(TokenSource::Value),
+            retries: parse("RAD_RETRIES", lookup("RAD_RETRIES"))?,
+            timeout: parse("RAD_TIMEOUT", lookup("RAD_TIMEOUT"))?,
+            target: lookup("RAD_TARGET"),
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct File {
+    profile: Option<String>,
+    #[serde(flatten)]
+    settings: Settings,
+    #[serde(default)]
+    profiles: HashMap<String, Settings>,
+}
+
+impl File {
+    fn load(path: &Path) -> Result<File, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content).map_err(|err| Error::Parse(path.into(), err)),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!(path = %path.display(), "Config file not found, using defaults");
+                Ok(File::default())
+            }
+            Err(err) => Err(Error::Io(path.into(), err)),
+        }
+    }
+}
+
+/// Fully resolved settings
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Config {
+    /// Install path when configured, see `Config::output`
+    pub(crate) output: Option<String>,
+    pub(crate) channel: Channel,
+    pub(crate) download_url: String,
+    pub(crate) api_url: String,
+    pub(crate) token: Option<TokenSource>,
+    pub(crate) retries: u32,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) target: String,
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("output", &self.output)
+            .field("channel", &self.channel)
+            .field("download_url", &self.download_url)
+            .field("api_url", &self.api_url)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("retries", &self.retries)
+            .field("timeout", &self.timeout)
+            .field("target", &self.target)
+            .finish()
+    }
+}
+
+fn default_config_path(paths: &Paths) -> Option<PathBuf> {
+    paths
+        .config_dir()
+        .map_err(|err| debug!("No config file: {}", err))
+        .ok()
+        .map(|dir| dir.join("config.toml"))
+}
+
+impl Config {
+    /// Loads the config file and resolves every setting from the process environment.
+    pub(crate) fn load(
+        cli: Settings,
+        profile: Option<String>,
+        path: Option<PathBuf>,
+        paths: &Paths,
+    ) -> Result<Config, Error> {
+        let lookup = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+
+        let file = match path
+            .or_else(|| lookup("RAD_CONFIG").map(PathBuf::from))
+            .or_else(|| default_config_path(paths))
+        {
+            Some(path) => File::load(&path)?,
+            None => File::default(),
+        };
+
+        Self::resolve(cli, profile.or_else(|| lookup("RAD_PROFILE")), lookup, file)
+    }
+
+    /// Configured install path or the default one, which can only fail when the
+    /// home directory is unknown, so only commands that need it call this.
+    pub(crate) fn output(&self, paths: &Paths) -> Result<String, Error> {
+        match &self.output {
+            Some(output) => Ok(output.clone()),
+            None => Ok(paths.install_path()?.to_string_lossy().into()),
+        }
+    }
+
+    fn resolve<F>(
+        cli: Settings,
+        profile: Option<String>,
+        env: F,
+        file: File,
+    ) -> Result<Config, Error>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let File {
+            profile: default_profile,
+            settings: base,
+            mut profiles,
+        } = file;
+
+        let profile = match profile.or(default_profile) {
+            Some(name) => match profiles.remove(&name) {
+                Some(profile) => {
+                    debug!(profile = name.as_str(), "Using config profile");
+                    profile
+                }
+                None => return Err(Error::UnknownProfile(name)),
+            },
+            None => Settings::default(),
+        };
+
+        let settings = cli.or(Settings::from_env(env)?).or(profile).or(base);
+
+        Ok(Config {
+            output: settings.output,
+            channel: settings.channel.unwrap_or(Channel::Stable),
+            download_url: settings
+                .download_url
+                .unwrap_or_else(|| DEFAULT_DOWNLOAD_URL.to_string()),
+            api_url: settings
+                .api_url
+                .unwrap_or_else(|| RELEASE_GITHUB_API_URL.to_string()),
+            token: settings.token,
+            retries: settings.retries.unwrap_or(0),
+            timeout: settings.timeout.map(Duration::from_secs),
+            target: settings.target.unwrap_or_else(|| host_target().to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_analyzer_downloader::rust_analyzer::version::BINARY_NAME;
+
+    const CONFIG: &str = r#"
+        retries = 1
+        channel = "nightly"
+        output = "/base/rust-analyzer"
+
+        [profiles.work]
+        output = "/work/rust-analyzer"
+        download_url = "https://mirror.example.com/download"
+        token = { value = "work-token" }
+
+        [profiles.personal]
+        channel = "stable"
+    "#;
+
+    fn file() -> File {
+        toml::from_str(CONFIG).unwrap()
+    }
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn paths() -> Paths {
+        Paths::new().with_root("/srv/rad")
+    }
+
+    #[test]
+    fn test_defaults_without_config() {
+        let config = Config::resolve(Settings::default(), None, no_env, File::default()).unwrap();
+
+        assert_eq!(config.output, None);
+        assert_eq!(
+            PathBuf::from(config.output(&paths()).unwrap()),
+            PathBuf::from("/srv/rad/bin").join(BINARY_NAME)
+        );
+        assert_eq!(config.channel, Channel::Stable);
+        assert_eq!(config.download_url, DEFAULT_DOWNLOAD_URL);
+        assert_eq!(config.api_url, RELEASE_GITHUB_API_URL);
+        assert_eq!(config.retries, 0);
+        assert_eq!(config.timeout, None);
+        assert_eq!(config.token, None);
+        assert_eq!(config.target, host_target());
+    }
+
+    #[test]
+    fn test_profile_overrides_top_level() {
+        let config =
+            Config::resolve(Settings::default(), Some("work".into()), no_env, file()).unwrap();
+
+        assert_eq!(config.output.as_deref(), Some("/work/rust-analyzer"));
+        assert_eq!(config.download_url, "https://mirror.example.com/download");
+        assert_eq!(config.token, Some(TokenSource::Value("work-token".into())));
+        assert_eq!(config.channel, Channel::Nightly);
+        assert_eq!(config.retries, 1);
+    }
+
+    #[test]
+    fn test_env_overrides_profile() {
+        let env = |name: &str| match name {
+            "RAD_OUTPUT_PATH" => Some("/env/rust-analyzer".to_string()),
+            "RAD_CHANNEL" => Some("nightly".to_string()),
+            _ => None,
+        };
+
+        let config =
+            Config::resolve(Settings::default(), Some("personal".into()), env, file()).unwrap();
+
+        assert_eq!(config.output.as_deref(), Some("/env/rust-analyzer"));
+        assert_eq!(config.channel, Channel::Nightly);
+    }
+
+    #[test]
+    fn test_cli_overrides_env() {
+        let env = |name: &str| match name {
+            "RAD_OUTPUT_PATH" => Some("/env/rust-analyzer".to_string()),
+            "RAD_RETRIES" => Some("5".to_string()),
+            _ => None,
+        };
+        let cli = Settings {
+            output: Some("/cli/rust-analyzer".into()),
+            ..Settings::default()
+        };
+
+        let config = Config::resolve(cli, Some("work".into()), env, file()).unwrap();
+
+        assert_eq!(config.output.as_deref(), Some("/cli/rust-analyzer"));
+        assert_eq!(config.retries, 5);
+    }
+
+    #[test]
+    fn test_token_is_resolved_on_use() {
+        let cli = Settings {
+            token: Some(TokenSource::Command("exit 1".into())),
+            ..Settings::default()
+        };
+
+        let config = Config::resolve(cli, None, no_env, File::default()).unwrap();
+
+        assert!(matches!(
+            config.token.unwrap().resolve(),
+            Err(Error::Token(_))
+        ));
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let result = Config::resolve(Settings::default(), Some("missing".into()), no_env, file());
+
+        assert!(matches!(result, Err(Error::UnknownProfile(name)) if name == "missing"));
+    }
+
+    #[test]
+    fn test_invalid_env_value() {
+        let env = |name: &str| (name == "RAD_TIMEOUT").then(|| "soon".to_string());
+
+        let result = Config::resolve(Settings::default(), None, env, File::default());
+
+        assert!(matches!(result, Err(Error::InvalidValue("RAD_TIMEOUT", _))));
+    }
+}
@@ -0,0 +1,269 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use clap::{Args, ValueEnum};
+use tracing_subscriber::{
+    filter::EnvFilter,
+    fmt::{layer as fmt_layer, writer::BoxMakeWriter, MakeWriter},
+    prelude::*,
+    registry, Layer, Registry,
+};
+
+const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+const DEFAULT_LEVEL: usize = 3;
+const ROTATED_FILES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct LogArgs {
+    /// Logs more, repeat for more detail (-vv). Overrides RUST_LOG
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Logs less, repeat to silence warnings (-qq). Overrides RUST_LOG
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    quiet: u8,
+
+    /// Also writes logs to this file
+    #[clap(long, global = true, value_parser)]
+    log_file: Option<PathBuf>,
+
+    /// Rotates the log file once it grows past this many megabytes
+    #[clap(long, global = true, value_parser, default_value_t = 10)]
+    log_max_size: u64,
+
+    /// Format of log lines
+    #[clap(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+}
+
+impl LogArgs {
+    fn level(&self) -> Option<&'static str> {
+        if self.verbose == 0 && self.quiet == 0 {
+            return None;
+        }
+
+        let level = (DEFAULT_LEVEL + self.verbose as usize).saturating_sub(self.quiet as usize);
+
+        Some(LEVELS[level.min(LEVELS.len() - 1)])
+    }
+
+    fn filter(&self) -> EnvFilter {
+        match self.level() {
+            Some(level) => EnvFilter::new(level),
+            None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        }
+    }
+}
+
+/// Log file that is renamed to `<path>.1` once it grows past `max_size` bytes,
+/// older files are shifted up to `<path>.3` and the oldest is dropped.
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    state: Mutex<(Option<File>, u64)>,
+}
+
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", index));
+
+    PathBuf::from(name)
+}
+
+fn ignore_not_found(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64) -> std::io::Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size,
+            state: Mutex::new((Some(file), size)),
+        })
+    }
+
+    fn rotate(&self, file: &mut Option<File>, size: &mut u64) -> std::io::Result<()> {
+        // Windows cannot rename a file that is still open
+        file.take();
+
+        for index in (1..ROTATED_FILES).rev() {
+            ignore_not_found(std::fs::rename(
+                rotated(&self.path, index),
+                rotated(&self.path, index + 1),
+            ))?;
+        }
+        ignore_not_found(std::fs::rename(&self.path, rotated(&self.path, 1)))?;
+
+        *file = Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?,
+        );
+        *size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for &RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let (file, size) = &mut *state;
+
+        if *size > 0 && *size + buf.len() as u64 > self.max_size {
+            self.rotate(file, size)?;
+        }
+
+        let written = match file {
+            Some(file) => file.write(buf)?,
+            None => return Err(ErrorKind::NotFound.into()),
+        };
+        *size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+
+        match &mut state.0 {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = &'a RotatingFile;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self
+    }
+}
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = fmt_layer()
+        .with_level(true)
+        .with_thread_names(false)
+        .with_target(false)
+        .with_writer(writer);
+
+    match format {
+        LogFormat::Text => layer.with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().with_ansi(false).boxed(),
+    }
+}
+
+/// Installs the global subscriber, logs go to stderr when stdout carries a JSON document.
+pub(crate) fn init(args: &LogArgs, stderr: bool) -> std::io::Result<()> {
+    let stream = if stderr {
+        atty::Stream::Stderr
+    } else {
+        atty::Stream::Stdout
+    };
+    let ansi = atty::is(stream) && std::env::var_os("NO_COLOR").is_none();
+
+    let writer = if stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
+    let mut layers = vec![layer(args.log_format, writer, ansi)];
+
+    if let Some(path) = &args.log_file {
+        let file = RotatingFile::open(path.clone(), args.log_max_size * 1024 * 1024)?;
+        layers.push(layer(args.log_format, file, false));
+    }
+
+    #[cfg(feature = "otel")]
+    match crate::telemetry::layer() {
+        Ok(Some(layer)) => layers.push(layer),
+        Ok(None) => {}
+        Err(err) => eprintln!("Failed to set up OTLP trace export: {}", err),
+    }
+
+    registry().with(layers).with(args.filter()).init();
+
+    Ok(())
+}
+
+/// Flushes log and trace exporters before the process exits.
+pub(crate) fn shutdown() {
+    #[cfg(feature = "otel")]
+    crate::telemetry::shutdown();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(verbose: u8, quiet: u8) -> LogArgs {
+        LogArgs {
+            verbose,
+            quiet,
+            log_file: None,
+            log_max_size: 10,
+            log_format: LogFormat::Text,
+        }
+    }
+
+    #[test]
+    fn test_level_from_flags() {
+        assert_eq!(args(0, 0).level(), None);
+        assert_eq!(args(1, 0).level(), Some("debug"));
+        assert_eq!(args(5, 0).level(), Some("trace"));
+        assert_eq!(args(0, 1).level(), Some("warn"));
+        assert_eq!(args(0, 9).level(), Some("off"));
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rad.log");
+        let file = RotatingFile::open(path.clone(), 10).unwrap();
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n", "fifth\n"] {
+            (&file).write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fifth\n");
+        assert_eq!(
+            std::fs::read_to_string(rotated(&path, 1)).unwrap(),
+            "fourth\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(rotated(&path, 3)).unwrap(),
+            "second\n"
+        );
+        assert!(!rotated(&path, 4).exists());
+    }
+}
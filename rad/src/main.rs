@@ -1,27 +1,27 @@
+use clap::Parser;
 use time::Instant;
 use tokio::runtime::Builder;
 use tracing::{debug, error};
-use tracing_subscriber::{filter::EnvFilter, fmt::layer as fmt_layer, prelude::*, registry};
 
+mod auth;
 mod commands;
+mod config;
+mod logging;
+mod shell;
+mod state;
+#[cfg(feature = "otel")]
+mod telemetry;
 
-use crate::commands::execute;
+use crate::commands::{execute, Cli, OutputFormat, EXIT_IO};
 
 fn main() {
     let start = Instant::now();
+    let args = Cli::parse();
 
-    let env_filter = EnvFilter::try_from_default_env()
-        .or_else(|_| EnvFilter::try_new("info"))
-        .unwrap();
-
-    let stdout_layer = fmt_layer()
-        .with_ansi(true)
-        .with_level(true)
-        .with_thread_names(false)
-        .with_target(false)
-        .with_writer(std::io::stdout);
-
-    registry().with(env_filter).with(stdout_layer).init();
+    if let Err(e) = logging::init(&args.logging, args.output_format == OutputFormat::Json) {
+        eprintln!("Failed to open log file: {}", e);
+        std::process::exit(EXIT_IO);
+    }
 
     let runtime = Builder::new_current_thread()
         .enable_io()
@@ -38,11 +38,16 @@ fn main() {
         }
     };
 
-    runtime.block_on(async {
-        let result = execute().await;
-
-        if let Err(e) = result {
-            error!("Some error has occurred: {}", e);
+    let code = runtime.block_on(async {
+        match execute(args).await {
+            Ok(code) => code,
+            Err(e) => {
+                error!("Some error has occurred: {}", e);
+                if let Some(hint) = e.hint() {
+                    error!("hint: {}", hint);
+                }
+                e.exit_code()
+            }
         }
     });
 
@@ -50,4 +55,7 @@ fn main() {
         "Command finished, exiting..., took {took}",
         took = start.elapsed()
     );
+
+    logging::shutdown();
+    std::process::exit(code);
 }
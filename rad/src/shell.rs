@@ -0,0 +1,213 @@
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use directories::BaseDirs;
+use rust_analyzer_downloader::fs::is_in_path;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    fn detect() -> Option<Self> {
+        let shell = std::env::var_os("SHELL")?;
+        let name = Path::new(&shell).file_name()?.to_str()?;
+
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+
+    fn rc_file(&self, home: &Path) -> PathBuf {
+        match self {
+            Shell::Bash => home.join(".bashrc"),
+            Shell::Zsh => std::env::var_os("ZDOTDIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.to_path_buf())
+                .join(".zshrc"),
+            Shell::Fish => home.join(".config").join("fish").join("config.fish"),
+        }
+    }
+
+    fn export_line(&self, dir: &Path) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!("export PATH=\"{}:$PATH\"", dir.display()),
+            Shell::Fish => format!("set -gx PATH \"{}\" $PATH", dir.display()),
+        }
+    }
+}
+
+/// Lines appended to the rc file, as a unified diff against its current content.
+fn diff(rc_file: &Path, content: &str, addition: &[String]) -> String {
+    let existing = content.lines().count();
+    let mut diff = format!(
+        "--- {0}\n+++ {0}\n@@ -{1},0 +{2},{3} @@\n",
+        rc_file.display(),
+        existing,
+        existing + 1,
+        addition.len()
+    );
+
+    for line in addition {
+        diff.push('+');
+        diff.push_str(line);
+        diff.push('\n');
+    }
+
+    diff
+}
+
+/// Asks on the terminal, Ctrl-C counts as no.
+async fn confirm(question: &str) -> bool {
+    eprint!("{} [y/N] ", question);
+    let _ = std::io::stderr().flush();
+
+    // Reading stdin blocks, on the blocking pool it leaves the runtime free to
+    // notice Ctrl-C while waiting for the answer
+    let answer = tokio::task::spawn_blocking(|| {
+        let mut answer = String::new();
+        std::io::stdin()
+            .lock()
+            .read_line(&mut answer)
+            .map(|_| answer)
+    });
+
+    tokio::select! {
+        answer = answer => match answer {
+            Ok(Ok(answer)) => matches!(answer.trim(), "y" | "Y" | "yes"),
+            _ => false,
+        },
+        _ = tokio::signal::ctrl_c() => {
+            eprintln!();
+            false
+        }
+    }
+}
+
+/// Offers to add the directory of the installed binary to `PATH`
+/// through the rc file of the current shell. Without `interactive`, or when
+/// stdin is not a terminal, only logs the line to add.
+pub(crate) async fn ensure_in_path(output: &str, interactive: bool) {
+    let dir = match Path::new(output).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => return,
+    };
+
+    if is_in_path(dir) {
+        debug!(dir = %dir.display(), "Output directory is on PATH");
+        return;
+    }
+
+    warn!(dir = %dir.display(), "Output directory is not on PATH");
+
+    let (shell, home) = match (Shell::detect(), BaseDirs::new()) {
+        (Some(shell), Some(dirs)) => (shell, dirs.home_dir().to_path_buf()),
+        _ => {
+            warn!(
+                "Unknown shell, add {} to PATH in your shell configuration",
+                dir.display()
+            );
+            return;
+        }
+    };
+
+    let rc_file = shell.rc_file(&home);
+    let line = shell.export_line(dir);
+    let content = std::fs::read_to_string(&rc_file).unwrap_or_default();
+
+    if content.lines().any(|existing| existing.trim() == line) {
+        info!(
+            rc_file = %rc_file.display(),
+            "PATH is already configured, restart your shell to pick it up"
+        );
+        return;
+    }
+
+    let mut addition = Vec::with_capacity(3);
+    if !content.is_empty() && !content.ends_with('\n') {
+        addition.push(String::new());
+    }
+    addition.push("# Added by rad (rust-analyzer-downloader)".to_string());
+    addition.push(line.clone());
+
+    if !interactive || atty::isnt(atty::Stream::Stdin) {
+        info!(
+            rc_file = %rc_file.display(),
+            "Add `{}` to your shell configuration",
+            line
+        );
+        return;
+    }
+
+    eprintln!("{}", diff(&rc_file, &content, &addition));
+
+    if !confirm(&format!(
+        "Add {} to PATH in {}?",
+        dir.display(),
+        rc_file.display()
+    ))
+    .await
+    {
+        info!(
+            rc_file = %rc_file.display(),
+            "PATH left unchanged, apply the diff above manually"
+        );
+        return;
+    }
+
+    let result = rc_file
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&rc_file)
+        })
+        .and_then(|mut file| writeln!(file, "{}", addition.join("\n")));
+
+    match result {
+        Ok(_) => info!(
+            rc_file = %rc_file.display(),
+            "PATH updated, restart your shell to pick it up"
+        ),
+        Err(err) => warn!(rc_file = %rc_file.display(), error = %err, "Failed to update rc file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_line() {
+        let dir = Path::new("/home/user/bin");
+
+        assert_eq!(
+            Shell::Bash.export_line(dir),
+            "export PATH=\"/home/user/bin:$PATH\""
+        );
+        assert_eq!(
+            Shell::Fish.export_line(dir),
+            "set -gx PATH \"/home/user/bin\" $PATH"
+        );
+    }
+
+    #[test]
+    fn test_diff_appends_after_existing_lines() {
+        let addition = vec!["# comment".to_string(), "export PATH=x".to_string()];
+        let diff = diff(Path::new("/home/user/.bashrc"), "a\nb\n", &addition);
+
+        assert_eq!(
+            diff,
+            "--- /home/user/.bashrc\n+++ /home/user/.bashrc\n@@ -2,0 +3,2 @@\n+# comment\n+export PATH=x\n"
+        );
+    }
+}
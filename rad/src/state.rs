@@ -0,0 +1,166 @@
+//! Result of the last successful release lookup, kept between runs so frequent
+//! callers such as editors and shell hooks do not hit the network every time.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use rust_analyzer_downloader::paths::Paths;
+use rust_analyzer_downloader::rust_analyzer::version::Version;
+use rust_analyzer_downloader::services::versions::ReleasesJsonResponse;
+
+/// Binary the last check looked at, valid while its modification time is unchanged.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Installed {
+    pub(crate) path: String,
+    pub(crate) modified: u64,
+    pub(crate) version: Version,
+    pub(crate) update_available: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct LastCheck {
+    /// Seconds since the Unix epoch
+    pub(crate) checked_at: u64,
+    /// Releases API the releases were fetched from
+    pub(crate) api_url: String,
+    pub(crate) releases: Vec<ReleasesJsonResponse>,
+    #[serde(default)]
+    pub(crate) installed: Option<Installed>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Modification time of `path` in seconds since the Unix epoch.
+pub(crate) fn modified(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|elapsed| elapsed.as_secs())
+}
+
+/// `last-check.json` in the state directory, `None` when it cannot be determined.
+pub(crate) fn default_path(paths: &Paths) -> Option<PathBuf> {
+    paths
+        .state_dir()
+        .map_err(|err| debug!("No state file: {}", err))
+        .ok()
+        .map(|dir| dir.join("last-check.json"))
+}
+
+/// Parses intervals such as `90s`, `30m`, `6h` or `1d`, plain numbers are seconds.
+pub(crate) fn parse_interval(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid interval '{}'", value))?;
+    let seconds = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}', use s, m, h or d", unit)),
+    };
+
+    amount
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("interval '{}' is too long", value))
+}
+
+impl LastCheck {
+    pub(crate) fn new(api_url: String, releases: Vec<ReleasesJsonResponse>) -> Self {
+        Self {
+            checked_at: now(),
+            api_url,
+            releases,
+            installed: None,
+        }
+    }
+
+    /// Reads the state file, a missing or unreadable file is treated as no previous check.
+    pub(crate) fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read(path).ok()?;
+
+        match serde_json::from_slice(&content) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                debug!(path = %path.display(), error = %err, "Ignoring unreadable state file");
+                None
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    /// Whether the check is younger than `interval` and came from the same releases API.
+    pub(crate) fn is_fresh(&self, api_url: &str, interval: Duration) -> bool {
+        let age = now().checked_sub(self.checked_at);
+
+        self.api_url == api_url && matches!(age, Some(age) if age < interval.as_secs())
+    }
+
+    /// What the last check found for the binary at `path`, `None` when it was
+    /// not checked or has been replaced since.
+    pub(crate) fn installed(&self, path: &str) -> Option<&Installed> {
+        self.installed.as_ref().filter(|installed| {
+            installed.path == path && modified(Path::new(path)) == Some(installed.modified)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("6h"), Ok(Duration::from_secs(6 * 3600)));
+        assert_eq!(parse_interval("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_interval("90"), Ok(Duration::from_secs(90)));
+        assert!(parse_interval("6w").is_err());
+        assert!(parse_interval("h").is_err());
+        assert!(parse_interval("999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_last_check_round_trip_and_freshness() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rad").join("last-check.json");
+        let api_url = "https://api.github.com/repos/rust-lang/rust-analyzer/releases";
+
+        LastCheck::new(api_url.into(), Vec::new())
+            .save(&path)
+            .unwrap();
+        let mut state = LastCheck::load(&path).unwrap();
+
+        assert!(state.is_fresh(api_url, Duration::from_secs(60)));
+        assert!(!state.is_fresh(
+            "https://mirror.example.com/releases",
+            Duration::from_secs(60)
+        ));
+
+        state.checked_at -= 120;
+        assert!(!state.is_fresh(api_url, Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,130 @@
+//! Exports spans to an OpenTelemetry collector over OTLP/HTTP.
+//!
+//! Export is enabled when `OTEL_EXPORTER_OTLP_ENDPOINT` or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set, the other standard
+//! `OTEL_*` variables (headers, timeout, service name) are honoured as well.
+
+use opentelemetry::{global, trace::TraceError, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::HttpExporterBuilder;
+use opentelemetry_sdk::{
+    trace::{config, TracerProvider},
+    Resource,
+};
+use tracing_subscriber::{Layer, Registry};
+
+const ENDPOINT_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+];
+
+fn provider(exporter: HttpExporterBuilder) -> Result<TracerProvider, TraceError> {
+    let exporter = exporter.build_span_exporter()?;
+
+    let resource = if std::env::var_os("OTEL_SERVICE_NAME").is_some() {
+        Resource::default()
+    } else {
+        Resource::default().merge(&Resource::new([KeyValue::new("service.name", "rad")]))
+    };
+
+    // The simple processor exports from its own thread with a blocking client,
+    // so it works outside of the current-thread runtime `rad` runs commands on
+    Ok(TracerProvider::builder()
+        .with_simple_exporter(exporter)
+        .with_config(config().with_resource(resource))
+        .build())
+}
+
+/// Layer that exports spans, `None` when no OTLP endpoint is configured.
+pub(crate) fn layer() -> Result<Option<Box<dyn Layer<Registry> + Send + Sync>>, TraceError> {
+    if ENDPOINT_VARS
+        .iter()
+        .all(|name| std::env::var_os(name).is_none())
+    {
+        return Ok(None);
+    }
+
+    let provider = provider(opentelemetry_otlp::new_exporter().http())?;
+    let tracer = provider.tracer("rad");
+    global::set_tracer_provider(provider);
+
+    Ok(Some(
+        tracing_opentelemetry::layer().with_tracer(tracer).boxed(),
+    ))
+}
+
+/// Flushes spans that are still buffered, must run before the process exits.
+pub(crate) fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_otlp::WithExportConfig;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use tracing_subscriber::prelude::*;
+
+    /// Accepts one OTLP/HTTP request and returns its request line and body size
+    fn receive(listener: TcpListener) -> (String, usize) {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+
+            if header.trim().is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+
+        (request_line, body.len())
+    }
+
+    #[test]
+    fn test_exports_spans_to_otlp_receiver() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let receiver = std::thread::spawn(move || receive(listener));
+
+        let provider = provider(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint),
+        )
+        .unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("rad-test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("download", version = "nightly").entered();
+        });
+
+        for result in provider.force_flush() {
+            result.unwrap();
+        }
+
+        let (request_line, body) = receiver.join().unwrap();
+
+        assert!(request_line.starts_with("POST /v1/traces "));
+        assert!(body > 0);
+    }
+}
@@ -0,0 +1,238 @@
+//! Synchronous wrappers around `Downloader` and `Versions`.
+//!
+//! Like `reqwest::blocking`, every type drives the async implementation on a
+//! private single threaded tokio runtime, so callers do not need a runtime of
+//! their own. They must not be used from within an async context, blocking
+//! there panics.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures_util::io::AllowStdIo;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::paths::Paths;
+use crate::services::cancel::CancellationToken;
+use crate::services::downloader::{
+    self, DownloaderBuilder as AsyncDownloaderBuilder, Error as DownloaderError, InstallReport,
+    TransferReport,
+};
+use crate::services::events::Observer;
+use crate::services::transport::Transport;
+use crate::services::versions::{
+    self, Error as VersionsError, Paging, VersionsBuilder as AsyncVersionsBuilder,
+};
+use crate::services::RetryPolicy;
+
+fn runtime() -> std::io::Result<Runtime> {
+    Builder::new_current_thread().enable_all().build()
+}
+
+/// Blocking `services::downloader::Downloader`.
+#[derive(Debug)]
+pub struct Downloader {
+    inner: downloader::Downloader,
+    runtime: Runtime,
+}
+
+/// Configures a blocking `Downloader`, see `services::downloader::DownloaderBuilder`.
+#[derive(Debug, Default)]
+pub struct DownloaderBuilder {
+    inner: AsyncDownloaderBuilder,
+}
+
+impl DownloaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends requests through `transport` instead of reqwest.
+    pub fn transport(self, transport: impl Transport + 'static) -> Self {
+        Self {
+            inner: self.inner.transport(transport),
+        }
+    }
+
+    pub fn base_url(self, base_url: impl Into<String>) -> Self {
+        Self {
+            inner: self.inner.base_url(base_url),
+        }
+    }
+
+    pub fn target(self, target: impl Into<String>) -> Self {
+        Self {
+            inner: self.inner.target(target),
+        }
+    }
+
+    pub fn asset(self, asset: impl Into<String>) -> Self {
+        Self {
+            inner: self.inner.asset(asset),
+        }
+    }
+
+    pub fn temp_dir(self, temp_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: self.inner.temp_dir(temp_dir),
+        }
+    }
+
+    pub fn paths(self, paths: Paths) -> Self {
+        Self {
+            inner: self.inner.paths(paths),
+        }
+    }
+
+    pub fn install_mode(self, mode: u32) -> Self {
+        Self {
+            inner: self.inner.install_mode(mode),
+        }
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.timeout(timeout),
+        }
+    }
+
+    pub fn retry_policy(self, retry: RetryPolicy) -> Self {
+        Self {
+            inner: self.inner.retry_policy(retry),
+        }
+    }
+
+    pub fn user_agent(self, user_agent: impl Into<String>) -> Self {
+        Self {
+            inner: self.inner.user_agent(user_agent),
+        }
+    }
+
+    /// Reports the progress of every download to `observer`.
+    pub fn observer(self, observer: impl Observer + 'static) -> Self {
+        Self {
+            inner: self.inner.observer(observer),
+        }
+    }
+
+    pub fn lock_timeout(self, lock_timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.lock_timeout(lock_timeout),
+        }
+    }
+
+    /// Aborts downloads once `token` is cancelled, eg. from another thread.
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            inner: self.inner.cancellation_token(token),
+        }
+    }
+
+    pub fn build(self) -> Result<Downloader, DownloaderError> {
+        let runtime = runtime().map_err(DownloaderError::Runtime)?;
+        // Builds inside the runtime in case the HTTP client needs one
+        let inner = runtime.block_on(async { self.inner.build() })?;
+
+        Ok(Downloader { inner, runtime })
+    }
+}
+
+impl Downloader {
+    pub fn builder() -> DownloaderBuilder {
+        DownloaderBuilder::new()
+    }
+
+    /// Downloads `version` and installs it at `output`, see `services::downloader::Downloader::download`.
+    pub fn download(&self, version: &str, output: &str) -> Result<InstallReport, DownloaderError> {
+        self.runtime.block_on(self.inner.download(version, output))
+    }
+
+    /// Writes the decompressed binary into `writer`, see `services::downloader::Downloader::download_to_writer`.
+    pub fn download_to_writer<W: Write>(
+        &self,
+        version: &str,
+        writer: &mut W,
+    ) -> Result<TransferReport, DownloaderError> {
+        let mut writer = AllowStdIo::new(writer);
+        self.runtime
+            .block_on(self.inner.download_to_writer(version, &mut writer))
+    }
+
+    pub fn download_to_bytes(&self, version: &str) -> Result<Vec<u8>, DownloaderError> {
+        self.runtime.block_on(self.inner.download_to_bytes(version))
+    }
+}
+
+/// Blocking `services::versions::Versions`.
+#[derive(Debug)]
+pub struct Versions {
+    inner: versions::Versions,
+    runtime: Runtime,
+}
+
+/// Configures blocking `Versions`, see `services::versions::VersionsBuilder`.
+#[derive(Debug, Default)]
+pub struct VersionsBuilder {
+    inner: AsyncVersionsBuilder,
+}
+
+impl VersionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends requests through `transport` instead of reqwest.
+    pub fn transport(self, transport: impl Transport + 'static) -> Self {
+        Self {
+            inner: self.inner.transport(transport),
+        }
+    }
+
+    pub fn api_url(self, api_url: impl Into<String>) -> Self {
+        Self {
+            inner: self.inner.api_url(api_url),
+        }
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.timeout(timeout),
+        }
+    }
+
+    pub fn retry_policy(self, retry: RetryPolicy) -> Self {
+        Self {
+            inner: self.inner.retry_policy(retry),
+        }
+    }
+
+    pub fn user_agent(self, user_agent: impl Into<String>) -> Self {
+        Self {
+            inner: self.inner.user_agent(user_agent),
+        }
+    }
+
+    pub fn cancellation_token(self, token: CancellationToken) -> Self {
+        Self {
+            inner: self.inner.cancellation_token(token),
+        }
+    }
+
+    pub fn build(self) -> Result<Versions, VersionsError> {
+        let runtime = runtime().map_err(VersionsError::Runtime)?;
+        let inner = runtime.block_on(async { self.inner.build() })?;
+
+        Ok(Versions { inner, runtime })
+    }
+}
+
+impl Versions {
+    pub fn builder() -> VersionsBuilder {
+        VersionsBuilder::new()
+    }
+
+    /// Fetches one page of releases, see `services::versions::Versions::get`.
+    pub fn get(&self, page: u32, per_page: u32) -> Result<Paging, VersionsError> {
+        self.runtime.block_on(self.inner.get(page, per_page))
+    }
+}
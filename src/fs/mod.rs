@@ -1,11 +1,44 @@
-#[cfg(feature = "tokio")]
-use tokio::io::{AsyncRead, AsyncWrite};
+use futures_util::io::{AsyncRead, AsyncWrite};
 
 pub(crate) async fn copy<'a, R, W>(reader: &'a mut R, writer: &'a mut W) -> std::io::Result<u64>
 where
     R: AsyncRead + Unpin,
     W: AsyncWrite + Unpin,
 {
-    #[cfg(feature = "tokio")]
-    return tokio::io::copy(reader, writer).await;
+    futures_util::io::copy(reader, writer).await
+}
+
+/// Reports whether `dir` is one of the directories listed in `PATH`.
+pub fn is_in_path(dir: &std::path::Path) -> bool {
+    let paths = match std::env::var_os("PATH") {
+        Some(paths) => paths,
+        None => return false,
+    };
+
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+    std::env::split_paths(&paths).any(|entry| {
+        entry
+            .canonicalize()
+            .map(|entry| entry == dir)
+            .unwrap_or(entry == dir)
+    })
+}
+
+/// Reports whether both paths point at the same file, following symlinks and,
+/// on Unix, hard links.
+pub fn same_file(left: &std::path::Path, right: &std::path::Path) -> bool {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        if let (Ok(left), Ok(right)) = (left.metadata(), right.metadata()) {
+            return left.dev() == right.dev() && left.ino() == right.ino();
+        }
+    }
+
+    match (left.canonicalize(), right.canonicalize()) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
 }
@@ -1,3 +1,7 @@
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod fs;
+pub mod paths;
+mod runtime;
 pub mod rust_analyzer;
 pub mod services;
@@ -0,0 +1,231 @@
+//! Directories used for temp files, state, configuration and the installed binary.
+//!
+//! Every directory is resolved on its own, the first source that provides it wins:
+//!
+//! 1. an explicit override, eg. `with_cache_dir`
+//! 2. a subdirectory of the root set with `with_root`
+//! 3. the matching XDG variable, eg. `$XDG_CACHE_HOME/rad`
+//! 4. the platform default below the home directory
+//!
+//! Nothing panics without a home directory, resolving a directory that has no
+//! source returns `Error::Unresolved` instead.
+
+use std::path::PathBuf;
+
+use directories::BaseDirs;
+use thiserror::Error as ThisError;
+
+use crate::rust_analyzer::version::BINARY_NAME;
+
+/// Subdirectory created in the cache, data, config and state directories.
+pub const APP_DIR: &str = "rad";
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error("could not determine the {0} directory, set {1} or HOME")]
+    Unresolved(&'static str, &'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Cache,
+    Data,
+    Config,
+    State,
+    Install,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Cache => "cache",
+            Kind::Data => "data",
+            Kind::Config => "config",
+            Kind::State => "state",
+            Kind::Install => "install",
+        }
+    }
+
+    fn variable(self) -> &'static str {
+        match self {
+            Kind::Cache => "XDG_CACHE_HOME",
+            Kind::Data => "XDG_DATA_HOME",
+            Kind::Config => "XDG_CONFIG_HOME",
+            Kind::State => "XDG_STATE_HOME",
+            Kind::Install => "XDG_BIN_HOME",
+        }
+    }
+
+    /// Subdirectory of the root.
+    fn root_dir(self) -> &'static str {
+        match self {
+            Kind::Install => "bin",
+            kind => kind.name(),
+        }
+    }
+
+    fn platform_dir(self, dirs: &BaseDirs) -> PathBuf {
+        match self {
+            Kind::Cache => dirs.cache_dir().join(APP_DIR),
+            Kind::Data => dirs.data_local_dir().join(APP_DIR),
+            Kind::Config => dirs.config_dir().join(APP_DIR),
+            Kind::State => dirs
+                .state_dir()
+                .unwrap_or_else(|| dirs.data_local_dir())
+                .join(APP_DIR),
+            Kind::Install => dirs.home_dir().join("bin"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Paths {
+    root: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    config_dir: Option<PathBuf>,
+    state_dir: Option<PathBuf>,
+    install_dir: Option<PathBuf>,
+}
+
+impl Paths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps every directory below `root`, eg. for containers or tests.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Temp files of downloads.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_config_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config_dir = Some(dir.into());
+        self
+    }
+
+    /// Results of previous runs, eg. the last release lookup.
+    pub fn with_state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Directory rust-analyzer is installed to.
+    pub fn with_install_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.install_dir = Some(dir.into());
+        self
+    }
+
+    pub fn cache_dir(&self) -> Result<PathBuf, Error> {
+        self.resolve(Kind::Cache, env)
+    }
+
+    pub fn data_dir(&self) -> Result<PathBuf, Error> {
+        self.resolve(Kind::Data, env)
+    }
+
+    pub fn config_dir(&self) -> Result<PathBuf, Error> {
+        self.resolve(Kind::Config, env)
+    }
+
+    pub fn state_dir(&self) -> Result<PathBuf, Error> {
+        self.resolve(Kind::State, env)
+    }
+
+    pub fn install_dir(&self) -> Result<PathBuf, Error> {
+        self.resolve(Kind::Install, env)
+    }
+
+    /// Default path of the installed rust-analyzer binary.
+    pub fn install_path(&self) -> Result<PathBuf, Error> {
+        Ok(self.install_dir()?.join(BINARY_NAME))
+    }
+
+    fn resolve<F>(&self, kind: Kind, env: F) -> Result<PathBuf, Error>
+    where
+        F: Fn(&str) -> Option<PathBuf>,
+    {
+        let explicit = match kind {
+            Kind::Cache => &self.cache_dir,
+            Kind::Data => &self.data_dir,
+            Kind::Config => &self.config_dir,
+            Kind::State => &self.state_dir,
+            Kind::Install => &self.install_dir,
+        };
+
+        if let Some(dir) = explicit {
+            return Ok(dir.clone());
+        }
+        if let Some(root) = &self.root {
+            return Ok(root.join(kind.root_dir()));
+        }
+        if let Some(dir) = env(kind.variable()) {
+            return Ok(match kind {
+                Kind::Install => dir,
+                _ => dir.join(APP_DIR),
+            });
+        }
+
+        BaseDirs::new()
+            .map(|dirs| kind.platform_dir(&dirs))
+            .ok_or(Error::Unresolved(kind.name(), kind.variable()))
+    }
+}
+
+/// Relative values are ignored, as the XDG specification requires.
+fn env(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_order() {
+        let env = |name: &str| match name {
+            "XDG_CACHE_HOME" => Some(PathBuf::from("/xdg/cache")),
+            "XDG_BIN_HOME" => Some(PathBuf::from("/xdg/bin")),
+            _ => None,
+        };
+
+        let paths = Paths::new();
+        assert_eq!(
+            paths.resolve(Kind::Cache, env).unwrap(),
+            PathBuf::from("/xdg/cache/rad")
+        );
+        assert_eq!(
+            paths.resolve(Kind::Install, env).unwrap(),
+            PathBuf::from("/xdg/bin")
+        );
+
+        let paths = Paths::new().with_root("/srv/rad");
+        assert_eq!(
+            paths.resolve(Kind::Cache, env).unwrap(),
+            PathBuf::from("/srv/rad/cache")
+        );
+        assert_eq!(
+            paths.resolve(Kind::Install, env).unwrap(),
+            PathBuf::from("/srv/rad/bin")
+        );
+
+        let paths = paths.with_cache_dir("/tmp/rad");
+        assert_eq!(
+            paths.resolve(Kind::Cache, env).unwrap(),
+            PathBuf::from("/tmp/rad")
+        );
+    }
+}
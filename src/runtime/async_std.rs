@@ -0,0 +1,45 @@
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+
+use async_compat::Compat;
+use async_process::Command;
+
+pub(crate) type File = async_std::fs::File;
+
+pub(crate) async fn create(path: &Path) -> IoResult<File> {
+    File::create(path).await
+}
+
+pub(crate) async fn open(path: &Path) -> IoResult<File> {
+    File::open(path).await
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    async_std::task::sleep(duration).await;
+}
+
+pub(crate) async fn unblock<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    async_std::task::spawn_blocking(f).await
+}
+
+/// Runs the program to completion, it is killed when the future is dropped.
+pub(crate) async fn command_output(program: &OsStr, args: &[&str]) -> IoResult<Output> {
+    Command::new(program)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+}
+
+/// reqwest needs a tokio reactor, async-compat provides one.
+pub(crate) fn tokio_context<F: Future>(future: F) -> Compat<F> {
+    Compat::new(future)
+}
@@ -0,0 +1,60 @@
+//! Runtime specific pieces: files, timers, blocking work and child processes.
+//!
+//! The backend is selected with the `tokio` (default), `async-std` or `smol`
+//! feature. When several are enabled tokio wins, then async-std. Files
+//! implement the `futures-io` traits whatever the backend.
+
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+
+#[cfg(feature = "tokio")]
+#[path = "tokio.rs"]
+mod imp;
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+#[path = "async_std.rs"]
+mod imp;
+
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+#[path = "smol.rs"]
+mod imp;
+
+#[cfg(not(any(feature = "tokio", feature = "async-std", feature = "smol")))]
+compile_error!("enable one of the `tokio`, `async-std` or `smol` features");
+
+pub(crate) use imp::{command_output, create, open, sleep, tokio_context, unblock};
+
+/// Runs `future` for at most `limit`, `None` when it did not finish in time.
+/// The future is dropped on timeout.
+pub(crate) async fn timeout<F: Future>(limit: Duration, future: F) -> Option<F::Output> {
+    let timer = sleep(limit);
+    pin_mut!(future, timer);
+
+    match select(future, timer).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+pub(crate) async fn create_dir_all(path: &Path) -> IoResult<()> {
+    let path = path.to_path_buf();
+    unblock(move || std::fs::create_dir_all(path)).await
+}
+
+pub(crate) async fn create_dir_with(builder: std::fs::DirBuilder, path: &Path) -> IoResult<()> {
+    let path = path.to_path_buf();
+    unblock(move || builder.create(path)).await
+}
+
+pub(crate) async fn set_permissions(
+    path: impl Into<PathBuf>,
+    permissions: std::fs::Permissions,
+) -> IoResult<()> {
+    let path = path.into();
+    unblock(move || std::fs::set_permissions(path, permissions)).await
+}
@@ -0,0 +1,48 @@
+use std::ffi::OsStr;
+use std::future::Future;
+use std::io::Result as IoResult;
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+
+use tokio::process::Command;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+
+pub(crate) type File = Compat<tokio::fs::File>;
+
+pub(crate) async fn create(path: &Path) -> IoResult<File> {
+    Ok(tokio::fs::File::create(path).await?.compat())
+}
+
+pub(crate) async fn open(path: &Path) -> IoResult<File> {
+    Ok(tokio::fs::File::open(path).await?.compat())
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+pub(crate) async fn unblock<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(output) => output,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Runs the program to completion, it is killed when the future is dropped.
+pub(crate) async fn command_output(program: &OsStr, args: &[&str]) -> IoResult<Output> {
+    Command::new(program)
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+}
+
+/// reqwest already runs on tokio.
+pub(crate) fn tokio_context<F: Future>(future: F) -> F {
+    future
+}
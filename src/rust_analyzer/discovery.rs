@@ -0,0 +1,431 @@
+use std::{
+    ffi::OsString,
+    fmt::{Display, Formatter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use directories::BaseDirs;
+use futures_util::future::join_all;
+use tracing::debug;
+
+use super::version::{inspect, is_executable, Error, Version, BINARY_NAME};
+use crate::fs::same_file;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Directory listed in `PATH` that is not one of the well-known locations
+    Path,
+    /// `$CARGO_HOME/bin`, e.g. installed with `cargo install`
+    Cargo,
+    /// Rustup proxy that dispatches to the active toolchain
+    RustupProxy,
+    /// `rust-analyzer` component of a rustup toolchain
+    Rustup(String),
+    /// Server bundled with the VS Code extension
+    VsCode,
+    /// Neovim mason package
+    Mason,
+    /// `~/bin`, default output of `rad`
+    HomeBin,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Path => write!(f, "PATH"),
+            Source::Cargo => write!(f, "cargo"),
+            Source::RustupProxy => write!(f, "rustup proxy"),
+            Source::Rustup(toolchain) => write!(f, "rustup ({})", toolchain),
+            Source::VsCode => write!(f, "vscode"),
+            Source::Mason => write!(f, "mason"),
+            Source::HomeBin => write!(f, "~/bin"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Installation {
+    pub path: PathBuf,
+    pub source: Source,
+    pub version: Result<Version, Error>,
+    /// Binary lives in a directory listed in `PATH`
+    pub on_path: bool,
+    /// Binary is the one that a shell resolves `rust-analyzer` to
+    pub active: bool,
+}
+
+#[derive(Debug)]
+struct Locations {
+    cargo_bin: Option<PathBuf>,
+    rustup_toolchains: Option<PathBuf>,
+    home_bin: Option<PathBuf>,
+    vscode_extensions: Vec<PathBuf>,
+    mason: Vec<PathBuf>,
+}
+
+impl Locations {
+    fn new() -> Self {
+        let base_dirs = BaseDirs::new();
+        let home = base_dirs.as_ref().map(|dirs| dirs.home_dir().to_path_buf());
+
+        let cargo_bin = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".cargo")))
+            .map(|cargo| cargo.join("bin"));
+
+        let rustup_toolchains = std::env::var_os("RUSTUP_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".rustup")))
+            .map(|rustup| rustup.join("toolchains"));
+
+        let vscode_extensions = home
+            .iter()
+            .flat_map(|home| {
+                [
+                    ".vscode",
+                    ".vscode-server",
+                    ".vscode-oss",
+                    ".vscode-insiders",
+                ]
+                .iter()
+                .map(move |dir| home.join(dir).join("extensions"))
+            })
+            .collect();
+
+        #[cfg(target_family = "windows")]
+        let nvim_data = "nvim-data";
+        #[cfg(target_family = "unix")]
+        let nvim_data = "nvim";
+
+        let mason = base_dirs
+            .as_ref()
+            .map(|dirs| dirs.data_local_dir().join(nvim_data).join("mason"))
+            .map(|mason| {
+                vec![
+                    mason.join("bin").join(BINARY_NAME),
+                    mason
+                        .join("packages")
+                        .join("rust-analyzer")
+                        .join(BINARY_NAME),
+                ]
+            })
+            .unwrap_or_default();
+
+        Self {
+            cargo_bin,
+            rustup_toolchains,
+            home_bin: home.map(|home| home.join("bin")),
+            vscode_extensions,
+            mason,
+        }
+    }
+
+    fn classify(&self, path: &Path) -> Source {
+        let dir = path.parent();
+
+        if self.is_rustup_proxy(path) {
+            Source::RustupProxy
+        } else if dir.is_some() && dir == self.cargo_bin.as_deref() {
+            Source::Cargo
+        } else if dir.is_some() && dir == self.home_bin.as_deref() {
+            Source::HomeBin
+        } else if self.mason.iter().any(|mason| mason == path) {
+            Source::Mason
+        } else {
+            Source::Path
+        }
+    }
+
+    fn rustup(&self) -> Option<PathBuf> {
+        #[cfg(target_family = "windows")]
+        let name = "rustup.exe";
+        #[cfg(target_family = "unix")]
+        let name = "rustup";
+
+        self.cargo_bin
+            .as_ref()
+            .map(|cargo_bin| cargo_bin.join(name))
+    }
+
+    /// Rustup installs every proxy as a hard link to (or a copy of) the `rustup`
+    /// binary, so the proxy has to be compared with `rustup` itself. Symlinked
+    /// proxies, eg. from a distribution package, resolve to a file named `rustup`.
+    fn is_rustup_proxy(&self, path: &Path) -> bool {
+        let symlinked = path
+            .canonicalize()
+            .ok()
+            .and_then(|real| {
+                real.file_stem()
+                    .map(|name| name == "rustup" || name == "rustup-init")
+            })
+            .unwrap_or(false);
+
+        symlinked
+            || self
+                .rustup()
+                .map(|rustup| rustup.is_file() && same_file(path, &rustup))
+                .unwrap_or(false)
+    }
+
+    fn well_known(&self) -> Vec<(PathBuf, Source)> {
+        let mut candidates = Vec::new();
+
+        if let Some(cargo_bin) = &self.cargo_bin {
+            let path = cargo_bin.join(BINARY_NAME);
+            let source = self.classify(&path);
+            candidates.push((path, source));
+        }
+
+        if let Some(toolchains) = &self.rustup_toolchains {
+            for toolchain in read_dir_sorted(toolchains) {
+                let name = toolchain
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                candidates.push((
+                    toolchain.join("bin").join(BINARY_NAME),
+                    Source::Rustup(name),
+                ));
+            }
+        }
+
+        for extensions in &self.vscode_extensions {
+            for extension in read_dir_sorted(extensions) {
+                let is_rust_analyzer = extension
+                    .file_name()
+                    .map(|name| {
+                        name.to_string_lossy()
+                            .starts_with("rust-lang.rust-analyzer-")
+                    })
+                    .unwrap_or(false);
+
+                if is_rust_analyzer {
+                    candidates.push((extension.join("server").join(BINARY_NAME), Source::VsCode));
+                }
+            }
+        }
+
+        for mason in &self.mason {
+            candidates.push((mason.clone(), Source::Mason));
+        }
+
+        if let Some(home_bin) = &self.home_bin {
+            candidates.push((home_bin.join(BINARY_NAME), Source::HomeBin));
+        }
+
+        candidates
+    }
+}
+
+fn read_dir_sorted(dir: &Path) -> Vec<PathBuf> {
+    let mut entries = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    entries.sort();
+    entries
+}
+
+/// Reports whether `path` is a rustup proxy that dispatches to the active toolchain.
+pub fn is_rustup_proxy(path: &Path) -> bool {
+    Locations::new().is_rustup_proxy(path)
+}
+
+/// Lists every `rust-analyzer` executable in `PATH` and in well-known
+/// install locations, in the order a shell would consider them.
+fn candidates(locations: &Locations, paths: Option<OsString>) -> Vec<(PathBuf, Source, bool)> {
+    let mut candidates: Vec<(PathBuf, Source, bool)> = Vec::new();
+
+    if let Some(paths) = paths {
+        for dir in std::env::split_paths(&paths) {
+            let path = dir.join(BINARY_NAME);
+            let source = locations.classify(&path);
+
+            if is_executable(&path) && !candidates.iter().any(|(known, _, _)| known == &path) {
+                candidates.push((path, source, true));
+            }
+        }
+    }
+
+    for (path, source) in locations.well_known() {
+        if is_executable(&path) && !candidates.iter().any(|(known, _, _)| known == &path) {
+            candidates.push((path, source, false));
+        }
+    }
+
+    candidates
+}
+
+/// Finds and inspects every `rust-analyzer` installation on the machine.
+#[tracing::instrument]
+pub async fn discover(limit: Duration) -> Vec<Installation> {
+    let candidates = candidates(&Locations::new(), std::env::var_os("PATH"));
+    debug!(candidates = ?candidates, "Found rust-analyzer candidates");
+
+    let versions = join_all(
+        candidates
+            .iter()
+            .map(|(path, _, _)| async move { inspect(path, limit).await }),
+    )
+    .await;
+
+    let mut has_active = false;
+
+    candidates
+        .into_iter()
+        .zip(versions)
+        .map(|((path, source, on_path), version)| {
+            let active = on_path && !has_active;
+            has_active |= active;
+
+            Installation {
+                path,
+                source,
+                version,
+                on_path,
+                active,
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn executable(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn locations(root: &Path) -> Locations {
+        Locations {
+            cargo_bin: Some(root.join("cargo").join("bin")),
+            rustup_toolchains: Some(root.join("rustup").join("toolchains")),
+            home_bin: Some(root.join("home").join("bin")),
+            vscode_extensions: vec![root.join("vscode").join("extensions")],
+            mason: vec![root.join("mason").join("bin").join(BINARY_NAME)],
+        }
+    }
+
+    #[test]
+    fn test_is_rustup_proxy_hard_link() {
+        let root = tempfile::tempdir().unwrap();
+        let locations = locations(root.path());
+        let cargo_bin = root.path().join("cargo").join("bin");
+        executable(&cargo_bin.join("rustup"));
+        std::fs::hard_link(cargo_bin.join("rustup"), cargo_bin.join(BINARY_NAME)).unwrap();
+
+        assert!(locations.is_rustup_proxy(&cargo_bin.join(BINARY_NAME)));
+        assert_eq!(
+            locations.classify(&cargo_bin.join(BINARY_NAME)),
+            Source::RustupProxy
+        );
+    }
+
+    #[test]
+    fn test_is_rustup_proxy_symlink() {
+        let root = tempfile::tempdir().unwrap();
+        let locations = locations(root.path());
+        executable(&root.path().join("usr").join("rustup"));
+        let proxy = root.path().join("usr").join(BINARY_NAME);
+        std::os::unix::fs::symlink(root.path().join("usr").join("rustup"), &proxy).unwrap();
+
+        assert!(locations.is_rustup_proxy(&proxy));
+    }
+
+    #[test]
+    fn test_is_not_rustup_proxy() {
+        let root = tempfile::tempdir().unwrap();
+        let locations = locations(root.path());
+        let cargo_bin = root.path().join("cargo").join("bin");
+        executable(&cargo_bin.join("rustup"));
+        executable(&cargo_bin.join(BINARY_NAME));
+
+        assert!(!locations.is_rustup_proxy(&cargo_bin.join(BINARY_NAME)));
+        assert_eq!(
+            locations.classify(&cargo_bin.join(BINARY_NAME)),
+            Source::Cargo
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let root = tempfile::tempdir().unwrap();
+        let locations = locations(root.path());
+
+        assert_eq!(
+            locations.classify(&root.path().join("home").join("bin").join(BINARY_NAME)),
+            Source::HomeBin
+        );
+        assert_eq!(
+            locations.classify(&root.path().join("mason").join("bin").join(BINARY_NAME)),
+            Source::Mason
+        );
+        assert_eq!(
+            locations.classify(&root.path().join("usr").join("bin").join(BINARY_NAME)),
+            Source::Path
+        );
+    }
+
+    #[test]
+    fn test_candidates_follow_path_order() {
+        let root = tempfile::tempdir().unwrap();
+        let locations = locations(root.path());
+        let first = root.path().join("first");
+        let second = root.path().join("second");
+        let toolchain = root
+            .path()
+            .join("rustup")
+            .join("toolchains")
+            .join("stable-x86_64-unknown-linux-gnu");
+        let extension = root
+            .path()
+            .join("vscode")
+            .join("extensions")
+            .join("rust-lang.rust-analyzer-0.3.1");
+        executable(&first.join(BINARY_NAME));
+        executable(&second.join(BINARY_NAME));
+        executable(&toolchain.join("bin").join(BINARY_NAME));
+        executable(&extension.join("server").join(BINARY_NAME));
+        std::fs::create_dir_all(root.path().join("empty")).unwrap();
+
+        let paths = std::env::join_paths([
+            root.path().join("empty"),
+            second.clone(),
+            first.clone(),
+            second.clone(),
+        ])
+        .unwrap();
+
+        let candidates = candidates(&locations, Some(paths));
+
+        assert_eq!(
+            candidates,
+            vec![
+                (second.join(BINARY_NAME), Source::Path, true),
+                (first.join(BINARY_NAME), Source::Path, true),
+                (
+                    toolchain.join("bin").join(BINARY_NAME),
+                    Source::Rustup("stable-x86_64-unknown-linux-gnu".into()),
+                    false
+                ),
+                (
+                    extension.join("server").join(BINARY_NAME),
+                    Source::VsCode,
+                    false
+                ),
+            ]
+        );
+    }
+}
@@ -1 +1,2 @@
+pub mod discovery;
 pub mod version;
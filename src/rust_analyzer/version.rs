@@ -1,12 +1,23 @@
 use std::{
     borrow::Cow,
+    ffi::OsStr,
     io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
-use tokio::process::Command;
+use tracing::debug;
 
-#[derive(Debug, PartialEq, Eq)]
+#[cfg(target_family = "windows")]
+pub const BINARY_NAME: &str = "rust-analyzer.exe";
+#[cfg(target_family = "unix")]
+pub const BINARY_NAME: &str = "rust-analyzer";
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Version {
     pub date_version: String,
     pub semantic_version: String,
@@ -14,14 +25,23 @@ pub struct Version {
 
 #[derive(Debug, ThisError)]
 pub enum Error {
-    #[error("An IO error occurred: {0}")]
-    Io(#[from] IoError),
+    /// `operation` on the binary at `path` failed, eg. `run` when it does not exist.
+    #[error("failed to {operation} {}: {source}", .path.display())]
+    Io {
+        operation: &'static str,
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
 
     #[error("Failed to execute command: {0}")]
     Command(String),
 
     #[error("Failed to parse rust-analyzer version, Reason: {0}")]
     Parse(String),
+
+    #[error("'{0}' did not finish within {1:?}")]
+    Timeout(String, Duration),
 }
 
 fn parse_version(output: Cow<'_, str>) -> Result<Version, Error> {
@@ -53,27 +73,70 @@ fn parse_version(output: Cow<'_, str>) -> Result<Version, Error> {
     }
 }
 
-pub async fn get<'a>() -> Result<Version, Error> {
-    let version = Command::new("rust-analyzer")
-        .arg("--version")
-        .output()
-        .await?;
+pub async fn get() -> Result<Version, Error> {
+    inspect("rust-analyzer", DEFAULT_TIMEOUT).await
+}
+
+/// Runs `<path> --version` and parses its output.
+///
+/// The child process is killed if it does not exit within `limit`,
+/// so a broken or hanging binary cannot block the caller forever.
+#[tracing::instrument(skip(path), fields(path = ?path.as_ref()))]
+pub async fn inspect<P: AsRef<OsStr>>(path: P, limit: Duration) -> Result<Version, Error> {
+    let command_line = format!("{} --version", Path::new(path.as_ref()).display());
+    debug!("Running {}", command_line);
+
+    let child = crate::runtime::command_output(path.as_ref(), &["--version"]);
+
+    let version = match crate::runtime::timeout(limit, child).await {
+        Some(output) => output.map_err(|source| Error::Io {
+            operation: "run",
+            path: PathBuf::from(path.as_ref()),
+            source,
+        })?,
+        None => return Err(Error::Timeout(command_line, limit)),
+    };
 
     if version.status.success() {
         let output = version.stdout.split(|b| b == &b'\n').next();
 
         match output {
             Some(value) => parse_version(String::from_utf8_lossy(value)),
-            None => Err(Error::Io(IoError::new(
-                ErrorKind::Other,
-                "No new line found in output",
-            ))),
+            None => Err(Error::Io {
+                operation: "read the version of",
+                path: PathBuf::from(path.as_ref()),
+                source: IoError::new(ErrorKind::Other, "No new line found in output"),
+            }),
         }
     } else {
-        Err(Error::Command("rust-analyzer --version".into()))
+        Err(Error::Command(command_line))
     }
 }
 
+/// Returns the first `rust-analyzer` executable found on `PATH`,
+/// the one a shell or an editor would run.
+pub fn which() -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(BINARY_NAME))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(target_family = "unix")]
+pub(crate) fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(target_family = "windows")]
+pub(crate) fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,139 @@
+//! Cooperative cancellation of downloads and release lookups.
+//!
+//! Cancelling stops the operation at the next await point. Temp files are
+//! removed and an install in progress leaves the previous binary untouched.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// Cancels every operation it was given to, clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+
+        let wakers = std::mem::take(&mut *self.lock());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once `cancel` has been called.
+    pub fn cancelled(&self) -> Cancelled<'_> {
+        Cancelled { token: self }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Waker>> {
+        self.inner
+            .wakers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled`.
+#[derive(Debug)]
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = self.token.lock();
+        // `cancel` may have run between the check above and taking the lock
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Runs `future` until it completes or `token` is cancelled, in which case
+/// the future is dropped and `None` is returned.
+pub(crate) async fn run<F: Future>(
+    token: Option<&CancellationToken>,
+    future: F,
+) -> Option<F::Output> {
+    let token = match token {
+        Some(token) => token,
+        None => return Some(future.await),
+    };
+    if token.is_cancelled() {
+        return None;
+    }
+
+    let cancelled = token.cancelled();
+    pin_mut!(future, cancelled);
+
+    match select(future, cancelled).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::task::{waker, ArcWake};
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    impl ArcWake for Counter {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_cancel_wakes_pending_operation() {
+        let counter = Arc::new(Counter::default());
+        let waker = waker(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let token = CancellationToken::new();
+        let operation = run(Some(&token), futures_util::future::pending::<()>());
+        pin_mut!(operation);
+
+        assert_eq!(operation.as_mut().poll(&mut cx), Poll::Pending);
+        token.clone().cancel();
+
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+        assert_eq!(operation.poll(&mut cx), Poll::Ready(None));
+    }
+}
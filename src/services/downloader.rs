@@ -1,84 +1,434 @@
-use async_compression::tokio::bufread::GzipDecoder;
+use async_compression::futures::bufread::GzipDecoder;
 use bytes::Bytes;
-use directories::BaseDirs;
-use futures_util::{Stream, StreamExt};
-use reqwest::Error as ReqwestError;
+use fs2::FileExt;
+use futures_util::io::{AsyncWrite, BufReader, Cursor};
+use futures_util::StreamExt;
+use reqwest::header::USER_AGENT;
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
 use std::io::Error as IoError;
-use std::{fmt::Debug, io::Cursor, path::PathBuf};
+use std::{
+    fmt::Debug,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use thiserror::Error as ThisError;
 
 #[cfg(target_family = "unix")]
-use std::{fs::Permissions, os::unix::prelude::PermissionsExt};
-use tokio::{
-    fs::File,
-    io::{AsyncWrite, BufReader},
+use std::{
+    fs::Permissions,
+    os::unix::{fs::DirBuilderExt, prelude::PermissionsExt},
 };
 use tracing::{debug, error, warn};
 
+use super::cancel::{self, CancellationToken};
+use super::events::{Event, Observer};
+use super::transport::{Error as TransportError, Request, ReqwestTransport, Response, Transport};
+use super::{RetryPolicy, DEFAULT_USER_AGENT};
+use crate::paths::Paths;
+use crate::runtime;
+
+pub const DEFAULT_DOWNLOAD_URL: &str =
+    "https://github.com/rust-lang/rust-analyzer/releases/download";
+
+/// Target triple of the rust-analyzer release asset matching this host.
+pub fn host_target() -> &'static str {
+    #[cfg(target_os = "windows")]
+    #[cfg(target_arch = "x86_64")]
+    return "x86_64-pc-windows-msvc";
+
+    #[cfg(target_os = "linux")]
+    #[cfg(target_arch = "x86_64")]
+    return "x86_64-unknown-linux-gnu";
+
+    #[cfg(target_os = "macos")]
+    #[cfg(target_arch = "aarch64")]
+    return "aarch64-apple-darwin";
+
+    #[cfg(target_os = "macos")]
+    #[cfg(target_arch = "x86_64")]
+    return "x86_64-apple-darwin";
+}
+
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Permissions of the installed binary on Unix.
+pub const DEFAULT_INSTALL_MODE: u32 = 0o755;
+
 #[derive(Debug)]
 pub struct Downloader {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
+    base_url: String,
+    target: String,
+    asset: Option<String>,
+    temp_dir: Option<PathBuf>,
+    paths: Paths,
+    mode: u32,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    user_agent: String,
+    observer: Option<Arc<dyn Observer>>,
+    cancel: Option<CancellationToken>,
+    lock_timeout: Duration,
+}
+
+/// Configures a `Downloader`, every setting defaults to downloading the
+/// rust-analyzer release for the host from GitHub.
+#[derive(Debug, Default)]
+pub struct DownloaderBuilder {
+    transport: Option<Arc<dyn Transport>>,
+    base_url: Option<String>,
+    target: Option<String>,
+    asset: Option<String>,
+    temp_dir: Option<PathBuf>,
+    paths: Paths,
+    mode: Option<u32>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    user_agent: Option<String>,
+    observer: Option<Arc<dyn Observer>>,
+    cancel: Option<CancellationToken>,
+    lock_timeout: Option<Duration>,
+}
+
+impl DownloaderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends requests with `client` instead of a default one.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.transport = Some(Arc::new(ReqwestTransport::new(client)));
+        self
+    }
+
+    /// Sends requests through `transport` instead of reqwest.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Downloads releases from a mirror of `DEFAULT_DOWNLOAD_URL`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Downloads the release asset built for `target` instead of the host.
+    pub fn target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Downloads `asset` instead of the rust-analyzer build for the target,
+    /// assets that do not end with `.gz` are installed as they are.
+    pub fn asset(mut self, asset: impl Into<String>) -> Self {
+        self.asset = Some(asset.into());
+        self
+    }
+
+    /// Directory for the compressed download, defaults to the cache directory of `paths`.
+    pub fn temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    /// Resolves the default temp directory, falling back to the system one
+    /// when no cache directory can be determined.
+    pub fn paths(mut self, paths: Paths) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Permissions of the installed binary, ignored outside of Unix.
+    pub fn install_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Timeout of a single request, including reading the response body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Reports the progress of every download to `observer`.
+    pub fn observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Aborts downloads with `Error::Cancelled` once `token` is cancelled.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// How long `download` waits while another process installs to the same
+    /// path, by default it fails right away with `Error::Locked`.
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = Some(lock_timeout);
+        self
+    }
+
+    pub fn build(self) -> Result<Downloader, Error> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(ReqwestTransport::new(
+                reqwest::Client::builder()
+                    .build()
+                    .map_err(|err| Error::Client(err.into()))?,
+            )),
+        };
+
+        Ok(Downloader {
+            transport,
+            base_url: self
+                .base_url
+                .unwrap_or_else(|| DEFAULT_DOWNLOAD_URL.to_string()),
+            target: self.target.unwrap_or_else(|| host_target().to_string()),
+            asset: self.asset,
+            temp_dir: self.temp_dir,
+            paths: self.paths,
+            mode: self.mode.unwrap_or(DEFAULT_INSTALL_MODE),
+            timeout: self.timeout,
+            retry: self.retry,
+            user_agent: self
+                .user_agent
+                .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            observer: self.observer,
+            cancel: self.cancel,
+            lock_timeout: self.lock_timeout.unwrap_or_default(),
+        })
+    }
 }
 
 #[derive(Debug, ThisError)]
 pub enum Error {
-    #[error(transparent)]
-    Network(#[from] ReqwestError),
+    #[error("failed to create the HTTP client: {0}")]
+    Client(#[source] TransportError),
+
+    /// Requesting or receiving the asset of release `version` from `url` failed.
+    #[error("failed to download {version} from {url}: {source}")]
+    Network {
+        url: String,
+        version: String,
+        #[source]
+        source: TransportError,
+    },
+
+    /// `operation` on `path` failed, eg. `create` on the staged binary.
+    #[error("failed to {operation} {}: {source}", .path.display())]
+    File {
+        operation: &'static str,
+        path: PathBuf,
+        #[source]
+        source: IoError,
+    },
+
+    #[error("download cancelled")]
+    Cancelled,
+
+    #[error("another install of {} is in progress", .0.display())]
+    Locked(PathBuf),
+
+    /// The blocking API could not start its runtime.
+    #[error("failed to start the runtime: {0}")]
+    Runtime(#[source] IoError),
+}
+
+impl Error {
+    /// Whether running the same download again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network { source, .. } => source.is_retryable(),
+            Error::Locked(_) => true,
+            Error::Client(_) | Error::File { .. } | Error::Cancelled | Error::Runtime(_) => false,
+        }
+    }
+
+    fn file(operation: &'static str, path: &Path) -> impl FnOnce(IoError) -> Error {
+        let path = path.to_path_buf();
+        move |source| Error::File {
+            operation,
+            path,
+            source,
+        }
+    }
 
-    #[error(transparent)]
-    File(#[from] IoError),
+    fn network(url: &str, version: &str) -> impl FnOnce(TransportError) -> Error {
+        let url = url.to_string();
+        let version = version.to_string();
+        move |source| Error::Network {
+            url,
+            version,
+            source,
+        }
+    }
+}
+
+fn as_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// What a single `Downloader::download_to_writer` run fetched, with the sizes,
+/// digest and timings gathered while streaming and decompressing the asset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransferReport {
+    pub url: String,
+    pub asset: String,
+    pub compressed_size: u64,
+    pub decompressed_size: u64,
+    /// SHA-256 of the downloaded asset, as lowercase hex
+    pub sha256: String,
+    #[serde(serialize_with = "as_secs")]
+    pub download_time: Duration,
+    #[serde(serialize_with = "as_secs")]
+    pub decompress_time: Duration,
+}
+
+impl TransferReport {
+    /// Download throughput in bytes per second.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.download_time.as_secs_f64();
+
+        if secs > 0.0 {
+            self.compressed_size as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What a single `Downloader::download` run fetched and where it was installed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstallReport {
+    #[serde(flatten)]
+    pub transfer: TransferReport,
+    pub path: PathBuf,
 }
 
 impl Downloader {
+    /// Downloader with default settings that sends requests with `client`.
     #[tracing::instrument]
     pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+        Self {
+            transport: Arc::new(ReqwestTransport::new(client)),
+            base_url: DEFAULT_DOWNLOAD_URL.to_string(),
+            target: host_target().to_string(),
+            asset: None,
+            temp_dir: None,
+            paths: Paths::new(),
+            mode: DEFAULT_INSTALL_MODE,
+            timeout: None,
+            retry: RetryPolicy::default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            observer: None,
+            cancel: None,
+            lock_timeout: Duration::ZERO,
+        }
+    }
+
+    pub fn builder() -> DownloaderBuilder {
+        DownloaderBuilder::new()
     }
 
     #[tracing::instrument]
-    fn get_file_name(&self) -> &'static str {
-        #[cfg(target_os = "windows")]
-        #[cfg(target_arch = "x86_64")]
-        return "rust-analyzer-x86_64-pc-windows-msvc.gz";
+    fn get_file_name(&self) -> String {
+        match &self.asset {
+            Some(asset) => asset.clone(),
+            None => format!("rust-analyzer-{}.gz", self.target),
+        }
+    }
 
-        #[cfg(target_os = "linux")]
-        #[cfg(target_arch = "x86_64")]
-        return "rust-analyzer-x86_64-unknown-linux-gnu.gz";
+    fn emit(&self, event: Event) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(&event);
+        }
+    }
 
-        #[cfg(target_os = "macos")]
-        #[cfg(target_arch = "aarch64")]
-        return "rust-analyzer-aarch64-apple-darwin.gz";
+    /// Reports the outcome of a download to the observer.
+    fn finish(&self, result: Result<TransferReport, Error>) -> Result<TransferReport, Error> {
+        match &result {
+            Ok(report) => self.emit(Event::Completed {
+                report: report.clone(),
+            }),
+            Err(Error::Cancelled) => self.emit(Event::Cancelled),
+            Err(err) => self.emit(Event::Failed {
+                error: err.to_string(),
+            }),
+        }
 
-        #[cfg(target_os = "macos")]
-        #[cfg(target_arch = "x86_64")]
-        return "rust-analyzer-x86_64-apple-darwin.gz";
+        result
     }
 
-    async fn decompress<S, O>(&self, stream: &mut S, output_file: &mut O) -> Result<(), Error>
+    async fn decompress<O>(
+        &self,
+        version: &str,
+        url: String,
+        response: Response,
+        output_file: &mut O,
+        path: Option<PathBuf>,
+    ) -> Result<TransferReport, Error>
     where
-        S: Stream<Item = Result<Bytes, reqwest::Error>> + Unpin,
         O: AsyncWrite + Unpin,
     {
-        let base_dirs = BaseDirs::new().unwrap();
-        let mut temp_file_path = PathBuf::new();
+        let total = response.content_length;
+        let mut stream = response.body;
 
-        temp_file_path.push(base_dirs.cache_dir());
-        temp_file_path.push("rust-analyzer.gz");
+        let mut temp_file_path = match &self.temp_dir {
+            Some(temp_dir) => temp_dir.clone(),
+            None => self.paths.cache_dir().unwrap_or_else(|err| {
+                debug!("{}, using the system temp directory", err);
+                std::env::temp_dir()
+            }),
+        };
+
+        runtime::create_dir_all(&temp_file_path)
+            .await
+            .map_err(Error::file("create the temp directory", &temp_file_path))?;
+        temp_file_path.push(unique_name(&format!("{}.download", self.get_file_name())));
         debug!("Temp file path: {}", temp_file_path.display());
 
-        let mut temp_file = File::create(&temp_file_path).await?;
+        // Declared before the file so the file is closed first
+        let _cleanup = Cleanup::new(&temp_file_path);
+        let mut temp_file = runtime::create(&temp_file_path)
+            .await
+            .map_err(Error::file("create", &temp_file_path))?;
+        let mut hasher = Sha256::new();
+        let mut compressed_size = 0;
+        let started = Instant::now();
 
         debug!("Copying Stream to Temp file");
         while let Some(chunk) = stream.next().await {
             let chunk_data: Bytes = match chunk {
                 Ok(chunk) => chunk,
                 Err(err) => {
-                    tokio::fs::remove_file(&temp_file_path).await?;
                     error!("Error while downloading: {}", err);
-                    return Err(Error::Network(err));
+                    return Err(Error::network(&url, version)(err));
                 }
             };
 
+            hasher.update(&chunk_data);
+            compressed_size += chunk_data.len() as u64;
+            self.emit(Event::Progress {
+                downloaded: compressed_size,
+                total,
+            });
+
             let mut cursor = Cursor::new(chunk_data);
             match crate::fs::copy(&mut cursor, &mut temp_file).await {
                 Ok(_) => {
@@ -89,21 +439,46 @@ impl Downloader {
                 }
                 Err(e) => {
                     error!("Some error has occurred while copying stream to temp file: {} TempFile {temp_file}", e, temp_file=temp_file_path.display());
-                    tokio::fs::remove_file(&temp_file_path).await?;
-                    return Err(Error::File(e));
+                    return Err(Error::file("write", &temp_file_path)(e));
                 }
             }
         }
+        let download_time = started.elapsed();
         debug!("Copying to TempFile finished");
 
+        let sha256 = format!("{:x}", hasher.finalize());
+        self.emit(Event::Verifying {
+            sha256: sha256.clone(),
+        });
+
         debug!("Starting decompression");
-        let mut gzip_decoder = GzipDecoder::new(BufReader::new(File::open(&temp_file_path).await?));
+        self.emit(Event::Installing { path });
+        let started = Instant::now();
+        let mut temp_file = BufReader::new(
+            runtime::open(&temp_file_path)
+                .await
+                .map_err(Error::file("open", &temp_file_path))?,
+        );
+
+        let result = if self.get_file_name().ends_with(".gz") {
+            crate::fs::copy(&mut GzipDecoder::new(temp_file), output_file).await
+        } else {
+            debug!("Asset is not compressed, copying as is");
+            crate::fs::copy(&mut temp_file, output_file).await
+        };
 
-        match crate::fs::copy(&mut gzip_decoder, output_file).await {
-            Ok(_) => {
+        match result {
+            Ok(decompressed_size) => {
                 debug!("Decompression finished, removing temp file");
-                tokio::fs::remove_file(&temp_file_path).await?;
-                Ok(())
+                Ok(TransferReport {
+                    url,
+                    asset: self.get_file_name(),
+                    compressed_size,
+                    decompressed_size,
+                    sha256,
+                    download_time,
+                    decompress_time: started.elapsed(),
+                })
             }
             Err(err) => {
                 error!(
@@ -111,42 +486,283 @@ impl Downloader {
                     err,
                     temp_file = temp_file_path.display()
                 );
-                tokio::fs::remove_file(&temp_file_path).await?;
-                Err(Error::File(err))
+                Err(Error::file("decompress", &temp_file_path)(err))
             }
         }
     }
 
+    #[tracing::instrument]
+    async fn create_output_dir(&self, output: &str) -> Result<(), Error> {
+        let parent = match Path::new(output).parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => return Ok(()),
+        };
+
+        if parent.exists() {
+            return Ok(());
+        }
+
+        debug!("Creating output directory {}", parent.display());
+        let mut builder = std::fs::DirBuilder::new();
+        builder.recursive(true);
+
+        #[cfg(target_family = "unix")]
+        builder.mode(0o755);
+
+        runtime::create_dir_with(builder, parent)
+            .await
+            .map_err(Error::file("create the directory", parent))?;
+
+        Ok(())
+    }
+
     #[tracing::instrument]
     fn get_download_url(&self, version: &str) -> String {
         format!(
-            "https://github.com/rust-lang/rust-analyzer/releases/download/{}/{}",
+            "{}/{}/{}",
+            self.base_url.trim_end_matches('/'),
             version,
             self.get_file_name(),
         )
     }
 
-    #[tracing::instrument]
-    pub async fn download(&self, version: &str, output: &str) -> Result<(), Error> {
+    /// Sends the request for `version`, nothing is written before the server answers.
+    async fn fetch(&self, version: &str) -> Result<(String, Response), Error> {
         let url = self.get_download_url(version);
         debug!("Downloading from: {url}", url = url);
-        let res = self.client.get(url).send().await?;
-        debug!("Response status: {status}", status = res.status());
+        self.emit(Event::Resolving { url: url.clone() });
+        let request = Request::new(url.as_str())
+            .header(USER_AGENT.as_str(), self.user_agent.as_str())
+            .timeout(self.timeout);
+
+        let res = super::send(self.transport.as_ref(), request, &self.retry)
+            .await
+            .map_err(Error::network(&url, version))?;
+        debug!("Response status: {status}", status = res.status);
+        self.emit(Event::DownloadStarted {
+            url: url.clone(),
+            total: res.content_length,
+        });
 
-        let mut stream = res.bytes_stream();
-        let mut file = File::create(output).await?;
+        Ok((url, res))
+    }
+
+    /// Downloads `version` and writes the decompressed binary into `writer`.
+    ///
+    /// On error `writer` may have received part of the binary, unlike
+    /// `download` nothing is cleaned up.
+    #[tracing::instrument(skip(writer))]
+    pub async fn download_to_writer<W>(
+        &self,
+        version: &str,
+        writer: &mut W,
+    ) -> Result<TransferReport, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let result = self.cancellable(async {
+            let (url, response) = self.fetch(version).await?;
+            self.decompress(version, url, response, writer, None).await
+        });
+
+        self.finish(result.await)
+    }
+
+    /// Downloads `version` and returns the decompressed binary.
+    #[tracing::instrument]
+    pub async fn download_to_bytes(&self, version: &str) -> Result<Vec<u8>, Error> {
+        let mut binary = Vec::new();
+        self.download_to_writer(version, &mut binary).await?;
+
+        Ok(binary)
+    }
+
+    /// Downloads `version` and installs it at `output`. The binary is staged
+    /// next to `output` and only replaces it once complete, so on error or
+    /// cancellation a previous install is left as it was.
+    #[tracing::instrument]
+    pub async fn download(&self, version: &str, output: &str) -> Result<InstallReport, Error> {
+        let result = self.cancellable(self.install(version, output)).await;
+
+        self.finish(result).map(|transfer| InstallReport {
+            transfer,
+            path: PathBuf::from(output),
+        })
+    }
+
+    async fn install(&self, version: &str, output: &str) -> Result<TransferReport, Error> {
+        self.create_output_dir(output).await?;
+        let output = Path::new(output);
+        let _lock = self.lock(output).await?;
+
+        let (url, response) = self.fetch(version).await?;
+        let staged = staged_path(output);
+        debug!("Staging install at {}", staged.display());
+
+        let cleanup = Cleanup::new(&staged);
+        let mut file = runtime::create(&staged)
+            .await
+            .map_err(Error::file("create", &staged))?;
 
         #[cfg(target_family = "unix")]
-        debug!("Setting permissions to file to 755 executable");
+        debug!("Setting permissions of the file to {:o}", self.mode);
         #[cfg(target_family = "unix")]
-        file.set_permissions(Permissions::from_mode(0o755)).await?;
+        runtime::set_permissions(&staged, Permissions::from_mode(self.mode))
+            .await
+            .map_err(Error::file("set permissions of", &staged))?;
+
+        let transfer = self
+            .decompress(
+                version,
+                url,
+                response,
+                &mut file,
+                Some(output.to_path_buf()),
+            )
+            .await?;
+        drop(file);
+
+        let target = output.to_path_buf();
+        runtime::unblock(move || std::fs::rename(staged, target))
+            .await
+            .map_err(Error::file("install", output))?;
+        cleanup.disarm();
 
-        match self.decompress(&mut stream, &mut file).await {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                tokio::fs::remove_file(output).await?;
-                Err(e)
+        Ok(transfer)
+    }
+
+    /// Takes the advisory lock guarding `output` against other installs, it is
+    /// released when the returned file is dropped.
+    async fn lock(&self, output: &Path) -> Result<std::fs::File, Error> {
+        let path = lock_path(output);
+        let file = runtime::unblock({
+            let path = path.clone();
+            move || {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(false)
+                    .open(path)
+            }
+        })
+        .await
+        .map_err(Error::file("open the lock", &path))?;
+        let started = Instant::now();
+
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(file),
+                Err(err) if err.kind() == fs2::lock_contended_error().kind() => {
+                    if started.elapsed() >= self.lock_timeout {
+                        return Err(Error::Locked(output.to_path_buf()));
+                    }
+                    debug!("Waiting for the install lock {}", path.display());
+                    runtime::sleep(LOCK_POLL_INTERVAL).await;
+                }
+                Err(err) => return Err(Error::file("lock", &path)(err)),
+            }
+        }
+    }
+
+    /// Runs `operation` until it finishes or the cancellation token fires.
+    async fn cancellable<T, F>(&self, operation: F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        match cancel::run(self.cancel.as_ref(), operation).await {
+            Some(result) => result,
+            None => {
+                debug!("Download cancelled");
+                Err(Error::Cancelled)
+            }
+        }
+    }
+}
+
+/// Appends the process id and a counter to `name`, so concurrent downloads in
+/// this and other processes never share a file.
+fn unique_name(name: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    format!(
+        "{}.{}-{}",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+fn hidden_sibling(output: &Path, suffix: &str) -> PathBuf {
+    let name = output
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    output.with_file_name(format!(".{}{}", name, suffix))
+}
+
+/// `.<name>.<pid>-<n>.partial` next to `output`.
+fn staged_path(output: &Path) -> PathBuf {
+    let staged = hidden_sibling(output, "");
+    let name = unique_name(&staged.to_string_lossy());
+
+    PathBuf::from(format!("{}.partial", name))
+}
+
+/// `.<name>.lock` next to `output`, it is left in place once released: a run
+/// waiting on the lock already has the file open, removing it would let a
+/// third run lock a new file at the same path alongside it.
+fn lock_path(output: &Path) -> PathBuf {
+    hidden_sibling(output, ".lock")
+}
+
+/// Removes a file when dropped, so errors, cancellation and dropped futures
+/// do not leave partial downloads behind.
+struct Cleanup(Option<PathBuf>);
+
+impl Cleanup {
+    fn new(path: &Path) -> Self {
+        Self(Some(path.to_path_buf()))
+    }
+
+    /// Keeps the file.
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            match std::fs::remove_file(&path) {
+                Ok(()) => debug!("Removed {}", path.display()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => warn!("Failed to remove {}: {}", path.display(), err),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_overrides_defaults() {
+        let downloader = Downloader::builder()
+            .base_url("https://mirror.example.com/releases/")
+            .target("aarch64-unknown-linux-gnu")
+            .install_mode(0o700)
+            .user_agent("editor-plugin/1.0")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            downloader.get_download_url("2022-08-22"),
+            "https://mirror.example.com/releases/2022-08-22/rust-analyzer-aarch64-unknown-linux-gnu.gz"
+        );
+        assert_eq!(downloader.mode, 0o700);
+        assert_eq!(downloader.user_agent, "editor-plugin/1.0");
+        assert_eq!(downloader.retry, RetryPolicy::default());
+    }
+}
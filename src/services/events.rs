@@ -0,0 +1,65 @@
+//! Typed install lifecycle events, so embedders can follow a `Downloader`
+//! without parsing `tracing` output.
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use super::downloader::TransferReport;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// The asset URL was resolved and is about to be requested.
+    Resolving {
+        url: String,
+    },
+    /// The server answered, `total` is the compressed size when it is known.
+    DownloadStarted {
+        url: String,
+        total: Option<u64>,
+    },
+    /// Another chunk of the compressed asset was received.
+    Progress {
+        downloaded: u64,
+        total: Option<u64>,
+    },
+    /// The whole asset was received, `sha256` is its digest.
+    Verifying {
+        sha256: String,
+    },
+    /// Decompression started, `path` is `None` when writing to a caller's writer.
+    Installing {
+        path: Option<PathBuf>,
+    },
+    Completed {
+        report: TransferReport,
+    },
+    Failed {
+        error: String,
+    },
+    /// The cancellation token fired, partial files were removed.
+    Cancelled,
+}
+
+/// Receives the events of every download, it is called inline and should return quickly.
+pub trait Observer: Debug + Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+/// Forwards events to a closure, eg. one sending them over a channel.
+pub struct FnObserver<F>(pub F);
+
+impl<F> Debug for FnObserver<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FnObserver(..)")
+    }
+}
+
+impl<F> Observer for FnObserver<F>
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn on_event(&self, event: &Event) {
+        (self.0)(event);
+    }
+}
@@ -1,2 +1,59 @@
+pub mod cancel;
 pub mod downloader;
+pub mod events;
+pub mod transport;
 pub mod versions;
+
+use std::time::Duration;
+
+use tracing::warn;
+
+use self::transport::{Error, Request, Response, Transport};
+
+/// `User-Agent` sent with every request unless a builder overrides it.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!("rust-analyzer-downloader/", env!("CARGO_PKG_VERSION"));
+
+/// How failed requests are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Additional attempts after the first one
+    pub retries: u32,
+    /// Delay before the first retry, it grows linearly with each attempt
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Sends the request, retrying on connection errors, timeouts and server
+/// errors as allowed by `policy`. Responses without a success status are errors.
+pub(crate) async fn send(
+    transport: &dyn Transport,
+    request: Request,
+    policy: &RetryPolicy,
+) -> Result<Response, Error> {
+    let mut attempt = 0;
+
+    loop {
+        let err = match transport.get(request.clone()).await {
+            Ok(response) if response.is_success() => return Ok(response),
+            Ok(response) => Error::Status(response.status),
+            Err(err) => err,
+        };
+
+        if !err.is_retryable() || attempt >= policy.retries {
+            return Err(err);
+        }
+
+        warn!(error = %err, attempt, "Request failed, retrying");
+        attempt += 1;
+        crate::runtime::sleep(policy.backoff * attempt).await;
+    }
+}
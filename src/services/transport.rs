@@ -0,0 +1,230 @@
+//! HTTP transport used by `Downloader` and `Versions`.
+//!
+//! `ReqwestTransport` is used by default, embedders can plug in their own HTTP
+//! stack by implementing `Transport`, and `MemoryTransport` serves canned
+//! responses for tests.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use thiserror::Error as ThisError;
+
+use crate::runtime;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Response body, read chunk by chunk.
+pub type Body = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>;
+
+#[derive(Debug, ThisError)]
+pub enum Error {
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("connection failed: {0}")]
+    Connect(BoxError),
+
+    #[error("request timed out")]
+    Timeout,
+
+    #[error("server responded with status {0}")]
+    Status(u16),
+
+    #[error(transparent)]
+    Other(BoxError),
+}
+
+impl Error {
+    /// Whether sending the request again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Reqwest(err) => err.is_connect() || err.is_timeout(),
+            Error::Connect(_) | Error::Timeout => true,
+            Error::Status(status) => *status >= 500,
+            Error::Other(_) => false,
+        }
+    }
+}
+
+/// A GET request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+}
+
+impl Request {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            headers: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    /// Size of the body when the server announced it
+    pub content_length: Option<u64>,
+    pub body: Body,
+}
+
+impl Debug for Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Response")
+            .field("status", &self.status)
+            .field("content_length", &self.content_length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Reads the whole body into memory.
+    pub async fn bytes(self) -> Result<Vec<u8>, Error> {
+        let mut body = self.body;
+        let mut data = Vec::new();
+
+        while let Some(chunk) = body.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+
+        Ok(data)
+    }
+}
+
+#[async_trait::async_trait]
+pub trait Transport: Debug + Send + Sync {
+    /// Sends a GET request, any status is returned as a `Response`.
+    async fn get(&self, request: Request) -> Result<Response, Error>;
+}
+
+/// Sends requests with a `reqwest::Client`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ReqwestTransport {
+    async fn get(&self, request: Request) -> Result<Response, Error> {
+        let mut builder = self.client.get(&request.url);
+
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        let response = runtime::tokio_context(builder.send()).await?;
+        let status = response.status().as_u16();
+        let content_length = response.content_length();
+        let chunks = Box::pin(response.bytes_stream());
+
+        // Every chunk is polled in the tokio context reqwest needs
+        let body = stream::unfold(chunks, |mut chunks| async move {
+            let chunk = runtime::tokio_context(chunks.next()).await?;
+            Some((chunk.map_err(Error::from), chunks))
+        });
+
+        Ok(Response {
+            status,
+            content_length,
+            body: Box::pin(body),
+        })
+    }
+}
+
+/// Serves canned responses from memory and records every request.
+#[derive(Debug, Default)]
+pub struct MemoryTransport {
+    responses: HashMap<String, (u16, Bytes)>,
+    chunk_size: Option<usize>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MemoryTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers GET requests for `url`, unknown URLs get a 404.
+    pub fn with_response(
+        mut self,
+        url: impl Into<String>,
+        status: u16,
+        body: impl Into<Bytes>,
+    ) -> Self {
+        self.responses.insert(url.into(), (status, body.into()));
+        self
+    }
+
+    /// Splits bodies into chunks of `chunk_size` bytes, like a network stream would.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for MemoryTransport {
+    async fn get(&self, request: Request) -> Result<Response, Error> {
+        let (status, body) = self
+            .responses
+            .get(&request.url)
+            .cloned()
+            .unwrap_or((404, Bytes::new()));
+
+        self.requests
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(request);
+
+        let chunk_size = self.chunk_size.unwrap_or(body.len()).max(1);
+        let chunks: Vec<_> = (0..body.len())
+            .step_by(chunk_size)
+            .map(|start| Ok(body.slice(start..(start + chunk_size).min(body.len()))))
+            .collect();
+
+        Ok(Response {
+            status,
+            content_length: Some(body.len() as u64),
+            body: Box::pin(stream::iter(chunks)),
+        })
+    }
+}
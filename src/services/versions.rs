@@ -1,14 +1,20 @@
-use reqwest::Error as ReqwestError;
-use serde::Deserialize;
+use reqwest::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
 use std::io::Error as IoError;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error as ThisError;
 use tracing::{debug, trace};
 
-const RELEASE_GITHUB_API_URL: &str =
+use super::cancel::{self, CancellationToken};
+use super::transport::{Error as TransportError, Request, ReqwestTransport, Transport};
+use super::{RetryPolicy, DEFAULT_USER_AGENT};
+
+pub const RELEASE_GITHUB_API_URL: &str =
     "https://api.github.com/repos/rust-lang/rust-analyzer/releases";
 const PER_PAGE: &str = "per_page";
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ReleasesJsonResponse {
     pub name: String,
     pub tag_name: String,
@@ -17,16 +23,131 @@ pub struct ReleasesJsonResponse {
 
 #[derive(Debug)]
 pub struct Versions {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
+    api_url: String,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    user_agent: String,
+    cancel: Option<CancellationToken>,
+}
+
+/// Configures `Versions`, every setting defaults to the GitHub Releases API
+/// of rust-analyzer.
+#[derive(Debug, Default)]
+pub struct VersionsBuilder {
+    transport: Option<Arc<dyn Transport>>,
+    api_url: Option<String>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    user_agent: Option<String>,
+    cancel: Option<CancellationToken>,
+}
+
+impl VersionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends requests with `client` instead of a default one.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.transport = Some(Arc::new(ReqwestTransport::new(client)));
+        self
+    }
+
+    /// Sends requests through `transport` instead of reqwest.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Lists releases from a mirror of `RELEASE_GITHUB_API_URL`.
+    pub fn api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = Some(api_url.into());
+        self
+    }
+
+    /// Timeout of a single request, including reading the response body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Aborts lookups with `Error::Cancelled` once `token` is cancelled.
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn build(self) -> Result<Versions, Error> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(ReqwestTransport::new(
+                reqwest::Client::builder()
+                    .build()
+                    .map_err(|err| Error::Client(err.into()))?,
+            )),
+        };
+
+        Ok(Versions {
+            transport,
+            api_url: self
+                .api_url
+                .unwrap_or_else(|| RELEASE_GITHUB_API_URL.to_string()),
+            timeout: self.timeout,
+            retry: self.retry,
+            user_agent: self
+                .user_agent
+                .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            cancel: self.cancel,
+        })
+    }
 }
 
 #[derive(Debug, ThisError)]
 pub enum Error {
-    #[error(transparent)]
-    Network(#[from] ReqwestError),
+    #[error("failed to create the HTTP client: {0}")]
+    Client(#[source] TransportError),
+
+    #[error("failed to fetch releases from {url}: {source}")]
+    Network {
+        url: String,
+        #[source]
+        source: TransportError,
+    },
+
+    #[error("Failed to parse releases from {url}: {source}")]
+    Parse {
+        url: String,
+        #[source]
+        source: serde_json::Error,
+    },
 
-    #[error(transparent)]
-    File(#[from] IoError),
+    #[error("release lookup cancelled")]
+    Cancelled,
+
+    /// The blocking API could not start its runtime.
+    #[error("failed to start the runtime: {0}")]
+    Runtime(#[source] IoError),
+}
+
+impl Error {
+    /// Whether looking the releases up again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network { source, .. } => source.is_retryable(),
+            Error::Client(_) | Error::Parse { .. } | Error::Cancelled | Error::Runtime(_) => false,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -36,27 +157,50 @@ pub enum Paging {
 }
 
 impl Versions {
+    /// Versions with default settings that sends requests with `client`.
     #[tracing::instrument]
     pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+        Self {
+            transport: Arc::new(ReqwestTransport::new(client)),
+            api_url: RELEASE_GITHUB_API_URL.to_string(),
+            timeout: None,
+            retry: RetryPolicy::default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            cancel: None,
+        }
+    }
+
+    pub fn builder() -> VersionsBuilder {
+        VersionsBuilder::new()
     }
 
     #[tracing::instrument]
     pub async fn get(&self, page: u32, per_page: u32) -> Result<Paging, Error> {
-        debug!("Sending request to {}", RELEASE_GITHUB_API_URL);
-        let response = self
-            .client
-            .get(RELEASE_GITHUB_API_URL)
+        debug!("Sending request to {}", self.api_url);
+        let separator = if self.api_url.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}{}={}", self.api_url, separator, PER_PAGE, per_page);
+        let request = Request::new(url.as_str())
             .header("Accept", "application/vnd.github+json")
-            .header("User-Agent", "rust-analyzer-downloader")
+            .header(USER_AGENT.as_str(), self.user_agent.as_str())
             .header("Accept-Encoding", "gzip")
             .header("Accept-Encoding", "deflate")
-            .query(&[(PER_PAGE, per_page)])
-            .send()
-            .await?;
+            .timeout(self.timeout);
 
-        trace!("Received response {:?}", response);
-        let data = response.json::<Vec<ReleasesJsonResponse>>().await?;
+        let fetch = async {
+            let response = super::send(self.transport.as_ref(), request, &self.retry).await?;
+            trace!("Received response {:?}", response);
+            response.bytes().await
+        };
+
+        let body = match cancel::run(self.cancel.as_ref(), fetch).await {
+            Some(body) => body.map_err(|source| Error::Network {
+                url: url.clone(),
+                source,
+            })?,
+            None => return Err(Error::Cancelled),
+        };
+        let data: Vec<ReleasesJsonResponse> =
+            serde_json::from_slice(&body).map_err(|source| Error::Parse { url, source })?;
         debug!("Versions: {:?}", data);
 
         if !data.is_empty() {
@@ -66,3 +210,39 @@ impl Versions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        let versions = Versions::builder().build().unwrap();
+
+        assert_eq!(versions.api_url, RELEASE_GITHUB_API_URL);
+        assert_eq!(versions.user_agent, DEFAULT_USER_AGENT);
+        assert_eq!(versions.timeout, None);
+        assert_eq!(versions.retry, RetryPolicy::default());
+    }
+
+    #[test]
+    fn test_builder_overrides_defaults() {
+        let retry = RetryPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(10),
+        };
+
+        let versions = Versions::builder()
+            .api_url("https://mirror.example.com/api/releases")
+            .user_agent("editor-plugin/1.0")
+            .timeout(Duration::from_secs(5))
+            .retry_policy(retry)
+            .build()
+            .unwrap();
+
+        assert_eq!(versions.api_url, "https://mirror.example.com/api/releases");
+        assert_eq!(versions.user_agent, "editor-plugin/1.0");
+        assert_eq!(versions.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(versions.retry, retry);
+    }
+}
@@ -1 +1,3 @@
+pub mod runtime;
 pub mod rust_analyzer_test;
+pub mod services_test;
@@ -0,0 +1,22 @@
+//! Runs async tests on the runtime selected by the crate features.
+
+use std::future::Future;
+
+#[cfg(feature = "tokio")]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    async_std::task::block_on(future)
+}
+
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    smol::block_on(future)
+}
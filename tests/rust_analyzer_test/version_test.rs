@@ -1,29 +1,35 @@
-use rust_analyzer_downloader::rust_analyzer::version::get;
-use tokio::process::Command;
+use std::process::Command;
 
-#[tokio::test]
-async fn test_get_command_not_found() {
-    let real_version = Command::new("./rust-analyzer")
-        .arg("--version")
-        .output()
-        .await;
+use rust_analyzer_downloader::rust_analyzer::version::{inspect, DEFAULT_TIMEOUT};
+
+use crate::runtime::block_on;
+
+#[test]
+fn test_get_command_not_found() {
+    let real_version = Command::new("./rust-analyzer").arg("--version").output();
 
     assert!(real_version.is_err());
 }
 
-#[tokio::test]
-async fn test_get_success() {
-    let real_version = Command::new("rust-analyzer")
+#[cfg(target_family = "unix")]
+#[test]
+fn test_get_success() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fake_binary(
+        dir.path(),
+        "echo 'rust-analyzer 0.3.1000-standalone (abcdef012 2022-06-01)'",
+    );
+
+    let real_version = Command::new(&path)
         .arg("--version")
         .output()
-        .await
         .unwrap()
         .stdout;
 
     let real_version = String::from_utf8(real_version).unwrap();
     let real_version = real_version.split(' ').collect::<Vec<&str>>();
 
-    let version = get().await;
+    let version = block_on(inspect(&path, DEFAULT_TIMEOUT));
 
     assert!(version.is_ok());
 
@@ -44,3 +50,58 @@ async fn test_get_success() {
             .to_owned()
     );
 }
+
+#[cfg(target_family = "unix")]
+fn fake_binary(dir: &std::path::Path, script: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join("rust-analyzer");
+    std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    path
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_inspect_binary_at_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fake_binary(
+        dir.path(),
+        "echo 'rust-analyzer 0.3.1000-standalone (abcdef012 2022-06-01)'",
+    );
+
+    let version = block_on(inspect(&path, DEFAULT_TIMEOUT)).unwrap();
+
+    assert_eq!(version.semantic_version, "0.3.1000-standalone");
+    assert_eq!(version.date_version, "2022-06-01");
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_inspect_times_out() {
+    use rust_analyzer_downloader::rust_analyzer::version::Error;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = fake_binary(dir.path(), "sleep 5");
+
+    let version = block_on(inspect(&path, Duration::from_millis(100)));
+
+    assert!(matches!(version, Err(Error::Timeout(_, _))));
+}
+
+#[test]
+fn test_inspect_not_found() {
+    use rust_analyzer_downloader::rust_analyzer::version::Error;
+    use std::io::ErrorKind;
+
+    let version = block_on(inspect("./does-not-exist/rust-analyzer", DEFAULT_TIMEOUT));
+
+    assert!(matches!(
+        version,
+        Err(Error::Io { operation: "run", path, source })
+            if source.kind() == ErrorKind::NotFound
+                && path == std::path::Path::new("./does-not-exist/rust-analyzer")
+    ));
+}
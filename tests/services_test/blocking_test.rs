@@ -0,0 +1,52 @@
+use rust_analyzer_downloader::blocking::{Downloader, Versions};
+use rust_analyzer_downloader::services::transport::MemoryTransport;
+use rust_analyzer_downloader::services::versions::Paging;
+
+#[test]
+fn test_blocking_versions_without_runtime() {
+    let versions = Versions::builder()
+        .api_url("https://api.example.com/releases")
+        .transport(MemoryTransport::new().with_response(
+            "https://api.example.com/releases?per_page=1",
+            200,
+            r#"[{"name": "nightly", "tag_name": "nightly", "prerelease": true}]"#,
+        ))
+        .build()
+        .unwrap();
+
+    match versions.get(1, 1).unwrap() {
+        Paging::Next(2, releases) => assert!(releases[0].prerelease),
+        paging => panic!("unexpected page {:?}", paging),
+    }
+}
+
+#[test]
+fn test_blocking_download_installs_plain_asset() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("rad");
+
+    let downloader = Downloader::builder()
+        .base_url("https://download.example.com")
+        .asset("rad-ubuntu-latest")
+        .temp_dir(dir.path())
+        .transport(MemoryTransport::new().with_response(
+            "https://download.example.com/v2.4.0/rad-ubuntu-latest",
+            200,
+            "binary",
+        ))
+        .build()
+        .unwrap();
+
+    let report = downloader
+        .download("v2.4.0", &output.to_string_lossy())
+        .unwrap();
+
+    assert_eq!(report.transfer.decompressed_size, 6);
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "binary");
+
+    let mut writer = Vec::new();
+    downloader
+        .download_to_writer("v2.4.0", &mut writer)
+        .unwrap();
+    assert_eq!(writer, b"binary");
+}
@@ -0,0 +1,78 @@
+use rust_analyzer_downloader::services::cancel::CancellationToken;
+use rust_analyzer_downloader::services::downloader::{Downloader, Error as DownloaderError};
+use rust_analyzer_downloader::services::events::{Event, FnObserver};
+use rust_analyzer_downloader::services::transport::MemoryTransport;
+use rust_analyzer_downloader::services::versions::{Error as VersionsError, Versions};
+
+use crate::runtime::block_on;
+
+const DOWNLOAD_URL: &str = "https://download.example.com";
+
+#[test]
+fn test_cancelled_install_keeps_previous_binary() {
+    block_on(async {
+        let install_dir = tempfile::tempdir().unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let output = install_dir.path().join("rust-analyzer");
+        std::fs::write(&output, "previous").unwrap();
+
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+
+        let downloader = Downloader::builder()
+            .base_url(DOWNLOAD_URL)
+            .asset("rust-analyzer")
+            .temp_dir(temp_dir.path())
+            .transport(
+                MemoryTransport::new()
+                    .with_response(
+                        format!("{}/nightly/rust-analyzer", DOWNLOAD_URL),
+                        200,
+                        vec![b'x'; 64 * 1024],
+                    )
+                    .with_chunk_size(1024),
+            )
+            .observer(FnObserver(move |event: &Event| {
+                if let Event::Progress { .. } = event {
+                    canceller.cancel();
+                }
+            }))
+            .cancellation_token(token)
+            .build()
+            .unwrap();
+
+        let result = downloader
+            .download("nightly", &output.to_string_lossy())
+            .await;
+
+        assert!(matches!(result, Err(DownloaderError::Cancelled)));
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "previous");
+        assert!(std::fs::read_dir(install_dir.path())
+            .unwrap()
+            .all(|entry| !entry
+                .unwrap()
+                .path()
+                .to_string_lossy()
+                .ends_with(".partial")));
+        assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+    })
+}
+
+#[test]
+fn test_cancelled_token_stops_release_lookup() {
+    block_on(async {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let versions = Versions::builder()
+            .transport(MemoryTransport::new())
+            .cancellation_token(token)
+            .build()
+            .unwrap();
+
+        assert!(matches!(
+            versions.get(1, 1).await,
+            Err(VersionsError::Cancelled)
+        ));
+    })
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use fs2::FileExt;
+use futures_util::future::join;
+use rust_analyzer_downloader::services::downloader::{Downloader, Error as DownloaderError};
+use rust_analyzer_downloader::services::transport::MemoryTransport;
+
+use crate::runtime::block_on;
+
+const DOWNLOAD_URL: &str = "https://download.example.com";
+
+fn downloader(
+    temp_dir: &std::path::Path,
+) -> rust_analyzer_downloader::services::downloader::DownloaderBuilder {
+    Downloader::builder()
+        .base_url(DOWNLOAD_URL)
+        .asset("rust-analyzer")
+        .temp_dir(temp_dir)
+        .transport(
+            MemoryTransport::new()
+                .with_response(
+                    format!("{}/nightly/rust-analyzer", DOWNLOAD_URL),
+                    200,
+                    "nightly",
+                )
+                .with_chunk_size(2),
+        )
+}
+
+#[test]
+fn test_concurrent_downloads_use_separate_temp_files() {
+    block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first").join("rust-analyzer");
+        let second = dir.path().join("second").join("rust-analyzer");
+        let downloader = downloader(&dir.path().join("cache")).build().unwrap();
+
+        let (left, right) = join(
+            downloader.download("nightly", &first.to_string_lossy()),
+            downloader.download("nightly", &second.to_string_lossy()),
+        )
+        .await;
+
+        left.unwrap();
+        right.unwrap();
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "nightly");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "nightly");
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("cache")).unwrap().count(),
+            0
+        );
+    })
+}
+
+#[test]
+fn test_locked_target_fails_or_waits() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("rust-analyzer");
+    let lock = std::fs::File::create(dir.path().join(".rust-analyzer.lock")).unwrap();
+    lock.lock_exclusive().unwrap();
+
+    let result = block_on(
+        downloader(dir.path())
+            .build()
+            .unwrap()
+            .download("nightly", &output.to_string_lossy()),
+    );
+    assert!(matches!(result, Err(DownloaderError::Locked(path)) if path == output));
+    assert!(!output.exists());
+
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        drop(lock);
+    });
+
+    let waiting = downloader(dir.path())
+        .lock_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    block_on(waiting.download("nightly", &output.to_string_lossy())).unwrap();
+
+    release.join().unwrap();
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), "nightly");
+}
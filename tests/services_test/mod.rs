@@ -0,0 +1,5 @@
+#[cfg(feature = "blocking")]
+pub mod blocking_test;
+pub mod cancel_test;
+pub mod lock_test;
+pub mod transport_test;
@@ -0,0 +1,255 @@
+use async_compression::futures::bufread::GzipEncoder;
+use futures_util::io::AsyncReadExt;
+use rust_analyzer_downloader::services::downloader::{Downloader, Error as DownloaderError};
+use rust_analyzer_downloader::services::events::{Event, FnObserver};
+use rust_analyzer_downloader::services::transport::{
+    Error as TransportError, MemoryTransport, Request, ReqwestTransport, Transport,
+};
+use rust_analyzer_downloader::services::versions::{Paging, Versions};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+use crate::runtime::block_on;
+
+const RELEASES_URL: &str = "https://api.example.com/releases";
+const DOWNLOAD_URL: &str = "https://download.example.com";
+
+async fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    GzipEncoder::new(data)
+        .read_to_end(&mut compressed)
+        .await
+        .unwrap();
+
+    compressed
+}
+
+#[test]
+fn test_versions_from_memory_transport() {
+    block_on(async {
+        let transport = MemoryTransport::new().with_response(
+            format!("{}?per_page=2", RELEASES_URL),
+            200,
+            r#"[{"name": "2022-08-22", "tag_name": "2022-08-22", "prerelease": false}]"#,
+        );
+
+        let versions = Versions::builder()
+            .api_url(RELEASES_URL)
+            .user_agent("test-agent")
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        match versions.get(1, 2).await.unwrap() {
+            Paging::Next(2, releases) => assert_eq!(releases[0].tag_name, "2022-08-22"),
+            paging => panic!("unexpected page {:?}", paging),
+        }
+    })
+}
+
+#[test]
+fn test_download_from_memory_transport() {
+    block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("bin").join("rust-analyzer");
+        let asset = gzip(b"#!/bin/sh\necho rust-analyzer\n").await;
+
+        let transport = MemoryTransport::new()
+            .with_response(
+                format!(
+                    "{}/2022-08-22/rust-analyzer-x86_64-unknown-linux-gnu.gz",
+                    DOWNLOAD_URL
+                ),
+                200,
+                asset.clone(),
+            )
+            .with_chunk_size(7);
+
+        let downloader = Downloader::builder()
+            .base_url(DOWNLOAD_URL)
+            .target("x86_64-unknown-linux-gnu")
+            .temp_dir(dir.path())
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        let report = downloader
+            .download("2022-08-22", &output.to_string_lossy())
+            .await
+            .unwrap();
+
+        assert_eq!(report.transfer.compressed_size, asset.len() as u64);
+        assert_eq!(report.transfer.decompressed_size, 29);
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "#!/bin/sh\necho rust-analyzer\n"
+        );
+    })
+}
+
+#[test]
+fn test_download_to_bytes_and_writer() {
+    block_on(async {
+        let binary = b"#!/bin/sh\necho rust-analyzer\n".to_vec();
+        let asset = gzip(&binary).await;
+        let url = format!(
+            "{}/nightly/rust-analyzer-aarch64-apple-darwin.gz",
+            DOWNLOAD_URL
+        );
+        let temp = tempfile::tempdir().unwrap();
+
+        let downloader = Downloader::builder()
+            .base_url(DOWNLOAD_URL)
+            .target("aarch64-apple-darwin")
+            .temp_dir(temp.path())
+            .transport(MemoryTransport::new().with_response(url.as_str(), 200, asset.clone()))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            downloader.download_to_bytes("nightly").await.unwrap(),
+            binary
+        );
+
+        let mut writer = futures_util::io::Cursor::new(Vec::new());
+        let report = downloader
+            .download_to_writer("nightly", &mut writer)
+            .await
+            .unwrap();
+
+        assert_eq!(writer.into_inner(), binary);
+        assert_eq!(report.url, url);
+        assert_eq!(report.sha256, format!("{:x}", Sha256::digest(&asset)));
+    })
+}
+
+#[test]
+fn test_download_emits_lifecycle_events() {
+    block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("rust-analyzer");
+        let asset = gzip(b"rust-analyzer").await;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+
+        let downloader = Downloader::builder()
+            .base_url(DOWNLOAD_URL)
+            .target("x86_64-unknown-linux-gnu")
+            .temp_dir(dir.path())
+            .transport(
+                MemoryTransport::new()
+                    .with_response(
+                        format!(
+                            "{}/nightly/rust-analyzer-x86_64-unknown-linux-gnu.gz",
+                            DOWNLOAD_URL
+                        ),
+                        200,
+                        asset.clone(),
+                    )
+                    .with_chunk_size(asset.len() / 2 + 1),
+            )
+            .observer(FnObserver(move |event: &Event| {
+                recorded.lock().unwrap().push(event.clone())
+            }))
+            .build()
+            .unwrap();
+
+        downloader
+            .download("nightly", &output.to_string_lossy())
+            .await
+            .unwrap();
+        let _ = downloader
+            .download("1999-01-01", &output.to_string_lossy())
+            .await;
+
+        let events = events.lock().unwrap();
+        let total = Some(asset.len() as u64);
+        assert!(matches!(&events[0], Event::Resolving { url } if url.ends_with(".gz")));
+        assert!(matches!(&events[1], Event::DownloadStarted { total: t, .. } if *t == total));
+        assert_eq!(
+            events[2..4],
+            [
+                Event::Progress {
+                    downloaded: asset.len() as u64 / 2 + 1,
+                    total
+                },
+                Event::Progress {
+                    downloaded: asset.len() as u64,
+                    total
+                },
+            ]
+        );
+        assert!(matches!(&events[4], Event::Verifying { .. }));
+        assert_eq!(
+            events[5],
+            Event::Installing {
+                path: Some(output.clone())
+            }
+        );
+        assert!(
+            matches!(&events[6], Event::Completed { report } if report.decompressed_size == 13)
+        );
+        assert!(matches!(&events[7], Event::Resolving { .. }));
+        assert!(matches!(&events[8], Event::Failed { error } if error.contains("404")));
+    })
+}
+
+#[test]
+fn test_download_missing_release_is_status_error() {
+    block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("rust-analyzer");
+
+        let downloader = Downloader::builder()
+            .base_url(DOWNLOAD_URL)
+            .temp_dir(dir.path())
+            .transport(MemoryTransport::new())
+            .build()
+            .unwrap();
+
+        let result = downloader
+            .download("1999-01-01", &output.to_string_lossy())
+            .await;
+
+        let err = result.unwrap_err();
+        assert!(matches!(
+            &err,
+            DownloaderError::Network {
+                version,
+                source: TransportError::Status(404),
+                ..
+            } if version == "1999-01-01"
+        ));
+        assert!(!err.is_retryable());
+        assert!(err.to_string().contains(DOWNLOAD_URL));
+        assert!(!output.exists());
+    })
+}
+
+#[test]
+fn test_reqwest_transport_streams_body() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/releases", listener.local_addr().unwrap());
+
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n[]")
+            .unwrap();
+    });
+
+    let response = block_on(async {
+        let response = ReqwestTransport::default()
+            .get(Request::new(url))
+            .await
+            .unwrap();
+        (response.status, response.bytes().await.unwrap())
+    });
+
+    server.join().unwrap();
+    assert_eq!(response, (200, b"[]".to_vec()));
+}